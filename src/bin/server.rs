@@ -0,0 +1,263 @@
+// 2つのクライアントを接続して対戦させるゲームサーバー
+// OPEN/START/MOVE/ACK/END/BYEのプロトコルを話す
+
+#![allow(clippy::needless_return)]
+
+use std::env;
+use std::io::{BufRead, BufReader, BufWriter, ErrorKind, Write};
+use std::net::{TcpListener, TcpStream};
+use std::time::{Duration, Instant};
+
+use reversi::board::*;
+
+// ENDで送る終局理由
+const REASON_DOUBLE_PASS: &str = "DOUBLE_PASS";
+const REASON_ILLEGAL_MOVE: &str = "ILLEGAL_MOVE";
+const REASON_TIMEOUT: &str = "TIMEOUT";
+const REASON_DISCONNECTED: &str = "DISCONNECTED";
+
+// 接続しているクライアント1つ分の情報
+struct Client {
+    name: String,
+    reader: BufReader<TcpStream>,
+    writer: BufWriter<TcpStream>,
+    connected: bool, // 接続が切れたらfalse（それ以降は送受信しない）
+    wins: u32,
+    loses: u32,
+    draws: u32,
+}
+
+// クライアントから受信した結果
+enum Received {
+    Line(String),
+    Timeout,
+    Disconnected,
+}
+
+impl Client {
+    fn new(stream: TcpStream) -> Client {
+        let reader = BufReader::new(stream.try_clone().expect("clone failure"));
+        let writer = BufWriter::new(stream);
+        return Client {
+            name: String::new(),
+            reader,
+            writer,
+            connected: true,
+            wins: 0,
+            loses: 0,
+            draws: 0,
+        };
+    }
+
+    // 1行受信する．timeoutを過ぎたらReceived::Timeout，接続が切れていたらReceived::Disconnectedを返す
+    fn read_line(&mut self, timeout: Option<Duration>) -> Received {
+        if !self.connected {
+            return Received::Disconnected;
+        }
+        if self.reader.get_ref().set_read_timeout(timeout).is_err() {
+            return self.disconnect("set_read_timeout failure");
+        }
+        let mut msg = String::new();
+        match self.reader.read_line(&mut msg) {
+            Ok(0) => return self.disconnect("connection closed"),
+            Ok(_) => {
+                println!("RECEIVED from {}: {}", self.name, msg.trim_end());
+                return Received::Line(msg);
+            },
+            Err(ref e) if e.kind() == ErrorKind::WouldBlock || e.kind() == ErrorKind::TimedOut => {
+                return Received::Timeout;
+            },
+            Err(e) => return self.disconnect(&format!("read failure: {}", e)),
+        }
+    }
+
+    // 接続が切れたものとして扱う
+    fn disconnect(&mut self, why: &str) -> Received {
+        println!("{} disconnected ({})", self.name, why);
+        self.connected = false;
+        return Received::Disconnected;
+    }
+
+    // 1行送信する．接続が切れていれば何もしない
+    fn write_line(&mut self, comment: &str) {
+        if !self.connected {
+            return;
+        }
+        let msg = format!("{}\n", comment);
+        if let Err(e) = self.writer.write_all(msg.as_bytes()).and_then(|_| self.writer.flush()) {
+            self.disconnect(&format!("write failure: {}", e));
+            return;
+        }
+        println!("SENT to {}: {}", self.name, comment);
+    }
+}
+
+// "A1"のような文字列を盤面のビットに変換する．不正な文字列ならNone
+fn parse_point(s: &str) -> Option<u64> {
+    let chars: Vec<char> = s.chars().collect();
+    if chars.len() != 2 {
+        return None;
+    }
+    let c1 = chars[0].to_ascii_uppercase();
+    if !('A'..='H').contains(&c1) || !('1'..='8').contains(&chars[1]) {
+        return None;
+    }
+    return Some(point_to_bit(c1, chars[1]));
+}
+
+// 1局対戦させる．clients[black]が黒番
+// 返り値は黒から見た勝敗（BLACK, WHITE, NONE）
+fn play_game(clients: &mut [Client], black: usize, time_limit: i32) -> i8 {
+    let white = 1 - black;
+    let mut left_time = [time_limit, time_limit];
+
+    let black_name = clients[black].name.clone();
+    let white_name = clients[white].name.clone();
+    clients[black].write_line(&format!("START BLACK {} {}", white_name, time_limit));
+    clients[white].write_line(&format!("START WHITE {} {}", black_name, time_limit));
+
+    let mut board_info = BoardInfo::new();
+    let mut board_info_history = Vec::<BoardInfo>::new();
+
+    let (winner, reason) = loop {
+        if is_game_over(&board_info) {
+            let (_black_count, _white_count, superior) = get_result(&board_info);
+            break (superior, REASON_DOUBLE_PASS);
+        }
+
+        // 手番のクライアント
+        let (mover, waiter) = if board_info.now_turn == BLACK { (black, white) } else { (white, black) };
+
+        let start = Instant::now();
+        let timeout = Duration::from_millis(std::cmp::max(left_time[mover], 1) as u64);
+        let msg = match clients[mover].read_line(Some(timeout)) {
+            Received::Line(msg) => msg,
+            Received::Timeout => break (-board_info.now_turn, REASON_TIMEOUT),
+            Received::Disconnected => break (-board_info.now_turn, REASON_DISCONNECTED), // 接続が切れた側の負け
+        };
+        left_time[mover] -= start.elapsed().as_millis() as i32;
+        if left_time[mover] <= 0 {
+            break (-board_info.now_turn, REASON_TIMEOUT);
+        }
+
+        let words: Vec<&str> = msg.split_whitespace().collect();
+        if words.len() == 1 && words[0] == "UNDO" { // 自分の直前の手まで2手戻す
+            if board_info_history.len() >= 2 {
+                board_info_history.pop();
+                board_info = board_info_history.pop().unwrap();
+                clients[waiter].write_line("UNDO");
+            }
+            continue;
+        }
+        if words.len() != 2 || words[0] != "MOVE" {
+            break (-board_info.now_turn, REASON_ILLEGAL_MOVE);
+        }
+
+        let bit = if words[1] == "PASS" {
+            0
+        }else{
+            match parse_point(words[1]) {
+                Some(bit) => bit,
+                None => break (-board_info.now_turn, REASON_ILLEGAL_MOVE),
+            }
+        };
+
+        let mut next_board_info = board_info.clone();
+        if place(bit, &mut next_board_info) == PLACE_ERR {
+            break (-board_info.now_turn, REASON_ILLEGAL_MOVE);
+        }
+        swap(&mut next_board_info);
+        board_info_history.push(board_info.clone());
+        board_info = next_board_info;
+        print_board_info_simply(&board_info);
+
+        clients[mover].write_line(&format!("ACK {}", left_time[mover]));
+        if !is_game_over(&board_info) { // 終局した場合は相手にMOVEを送らずENDを送る
+            clients[waiter].write_line(&format!("MOVE {}", words[1].to_ascii_uppercase()));
+        }
+    };
+
+    let (black_count, white_count, _superior) = get_result(&board_info);
+    let result_word = |color: i8| -> &str {
+        return if winner == NONE {"TIE"} else if winner == color {"WIN"} else {"LOSE"};
+    };
+    clients[black].write_line(&format!("END {} {} {} {}", result_word(BLACK), black_count, white_count, reason));
+    clients[white].write_line(&format!("END {} {} {} {}", result_word(WHITE), white_count, black_count, reason));
+
+    match winner {
+        BLACK => { clients[black].wins += 1; clients[white].loses += 1; },
+        WHITE => { clients[white].wins += 1; clients[black].loses += 1; },
+        _     => { clients[black].draws += 1; clients[white].draws += 1; },
+    };
+    return winner;
+}
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+
+    let mut port = "3000";
+    let mut time_limit: i32 = 60000;
+    let mut games: u32 = 1;
+
+    let mut i = 1;
+    while i < args.len() {
+        if args[i] == "-p" { // port
+            port = &args[i+1];
+            i += 2;
+        }else if args[i] == "-t" { // 持ち時間（ms）
+            time_limit = args[i+1].parse().expect("INVALID time");
+            i += 2;
+        }else if args[i] == "-g" { // 対局数
+            games = args[i+1].parse().expect("INVALID number of games");
+            i += 2;
+        }else {
+            panic!("INVALID args");
+        }
+    }
+
+    let listener = TcpListener::bind(format!("0.0.0.0:{}", port)).expect("bind failure");
+    println!("waiting for clients on port {}", port);
+
+    // 2つのクライアントの接続を待ち，OPENを受け取る
+    let mut clients = Vec::<Client>::new();
+    while clients.len() < 2 {
+        let (stream, addr) = listener.accept().expect("accept failure");
+        let mut client = Client::new(stream);
+        client.name = format!("{}", addr);
+        let msg = match client.read_line(None) {
+            Received::Line(msg) => msg,
+            Received::Timeout | Received::Disconnected => continue, // OPENを送らずに切れた接続は捨てて待ち直す
+        };
+        let words: Vec<&str> = msg.split_whitespace().collect();
+        if words.len() != 2 || words[0] != "OPEN" {
+            println!("could not receive 'OPEN' from {}", client.name);
+            continue;
+        }
+        client.name = words[1].to_string();
+        println!("{} joined", client.name);
+        clients.push(client);
+    }
+
+    // 先手後手を入れ替えながら対局する
+    for game in 0..games {
+        let black = (game % 2) as usize;
+        let winner = play_game(&mut clients, black, time_limit);
+        println!("GAME {}: {}", game + 1, match winner {
+            BLACK => format!("{} (BLACK) won", clients[black].name),
+            WHITE => format!("{} (WHITE) won", clients[1 - black].name),
+            _     => "draw".to_string(),
+        });
+        if clients.iter().any(|client| !client.connected) { // 残りの対局はできない
+            println!("a client disconnected: stop playing");
+            break;
+        }
+    }
+
+    let summary = format!("{} {} {} {} {} {} {} {}",
+        clients[0].name, clients[0].wins, clients[0].loses, clients[0].draws,
+        clients[1].name, clients[1].wins, clients[1].loses, clients[1].draws,
+    );
+    for client in clients.iter_mut() {
+        client.write_line(&format!("BYE {}", summary));
+    }
+}
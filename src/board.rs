@@ -0,0 +1,623 @@
+pub const BLACK: i8 = 1;
+pub const WHITE: i8 = -1;
+pub const NONE : i8 = 0;
+pub const TURN_CHANGE_FACTOR: i8 = -1;
+pub const BOARDSIZE: i32 = 64;
+pub const LINESIZE: i32 = 8;
+pub const MAX_TURNS: i8 = 60;
+
+// placeの返り値で用いる
+pub const CONTINUE: i8 = 0;
+pub const PLACE_ERR: i8 = -1;
+pub const GAME_SET: i8 = 1;
+
+pub struct BoardInfo {
+    pub now_turn: i8,
+    pub now_index: i8,
+    pub player_board: u64,
+    pub opponent_board: u64,
+}
+
+impl BoardInfo {
+    // 初期盤面（黒番）を返す
+    pub fn new() -> BoardInfo {
+        return BoardInfo {
+            now_turn: BLACK,
+            now_index: 1,
+            player_board: 0x0000000810000000,
+            opponent_board: 0x0000001008000000,
+        };
+    }
+}
+
+impl Default for BoardInfo {
+    fn default() -> Self {
+        return BoardInfo::new();
+    }
+}
+
+impl Clone for BoardInfo {
+    fn clone(&self) -> Self {
+        return BoardInfo {
+            now_turn: self.now_turn.clone(),
+            now_index: self.now_index.clone(),
+            player_board: self.player_board.clone(),
+            opponent_board: self.opponent_board.clone(),
+        };
+    }
+}
+
+// 盤面の情報を簡易的に出力する
+pub fn print_board_info_simply(board_info: &BoardInfo) -> () {
+    let (black_count, white_count, _superior) = get_result(&board_info);
+    println!("{}'s TURN, BLACK:{}, WHITE:{}, INDEX:{}", 
+        match board_info.now_turn {
+            BLACK => "BLACK",
+            WHITE => "WHITE",
+            _     => panic!("there is not sych color"),
+        },
+        black_count,
+        white_count,
+        board_info.now_index,
+    );
+}
+
+// 盤面を出力する
+pub fn print_board_info(board_info: &BoardInfo, eval: i32) -> () {
+    println!("");
+    println!("********************");
+    let mut player_color: char = 'b';
+    let mut opponent_color: char = 'w';
+    let (black_count, white_count, _superior) = get_result(&board_info);
+    println!("{}'s TURN, BLACK:{}, WHITE:{}, INDEX:{}, PLAYER's EVAL:{}", 
+        match board_info.now_turn {
+            BLACK => "BLACK",
+            WHITE => {
+                player_color = 'w';
+                opponent_color = 'b';
+                "WHITE"
+            },
+            _     => panic!("there is not sych color"),
+        },
+        black_count,
+        white_count,
+        board_info.now_index,
+        eval
+    );
+    println!("");
+    println!("   A B C D E F G H ");
+    for i in (0..8).rev() {
+        print!(" {} ", i32::abs(8-i));
+        for j in (0..8).rev() {
+            if ((board_info.player_board >> (i*8)+j) & (1 as u64)) == 1 {
+                print!("{} ", player_color);
+            }else if ((board_info.opponent_board >> (i*8)+j) & (1 as u64)) == 1 {
+                print!("{} ", opponent_color);
+            }else{
+                print!("- ");
+            }
+        }
+        println!("");
+    }
+    println!("");
+    println!("POSITION: {}", board_info_to_string(board_info));
+    println!("********************");
+}
+
+// 局面を"---------------------------OX------XO--------------------------- X"のような文字列にする
+// A1，B1，…，H8の順に64マス（Xが黒，Oが白，-が空き），空白をはさんで手番（XかO）
+pub fn board_info_to_string(board_info: &BoardInfo) -> String {
+    let (black_board, white_board) = if board_info.now_turn == BLACK {
+        (board_info.player_board, board_info.opponent_board)
+    }else{
+        (board_info.opponent_board, board_info.player_board)
+    };
+    let mut s = String::new();
+    for i in 0..BOARDSIZE {
+        let bit = 1u64 << (63 - i);
+        s.push(if black_board & bit != 0 {'X'} else if white_board & bit != 0 {'O'} else {'-'});
+    }
+    s.push(' ');
+    s.push(if board_info.now_turn == BLACK {'X'} else {'O'});
+    return s;
+}
+
+// board_info_to_stringの逆，小文字や*（黒），.（空き）も許し，空白と;以降は無視する
+// 石が初期局面より少なければエラーを返す（now_indexが負にならないように）
+pub fn string_to_board_info(s: &str) -> Result<BoardInfo, String> {
+    let chars: Vec<char> = s.split(';').next().unwrap().chars().filter(|c| !c.is_whitespace()).collect();
+    if chars.len() != BOARDSIZE as usize + 1 {
+        return Err(format!("expected {} squares and the side to move: {}", BOARDSIZE, s));
+    }
+    let mut black_board: u64 = 0;
+    let mut white_board: u64 = 0;
+    for (i, &c) in chars[..BOARDSIZE as usize].iter().enumerate() {
+        let bit = 1u64 << (63 - i);
+        match c {
+            'X' | 'x' | '*' => black_board |= bit,
+            'O' | 'o' => white_board |= bit,
+            '-' | '.' => (),
+            _ => return Err(format!("invalid square '{}': {}", c, s)),
+        };
+    }
+    let now_turn = match chars[BOARDSIZE as usize] {
+        'X' | 'x' | '*' => BLACK,
+        'O' | 'o' => WHITE,
+        c => return Err(format!("invalid side to move '{}': {}", c, s)),
+    };
+    let (player_board, opponent_board) = if now_turn == BLACK {(black_board, white_board)} else {(white_board, black_board)};
+    let mut board_info = boards_to_board_info(player_board, opponent_board).map_err(|e| format!("{}: {}", e, s))?;
+    board_info.now_turn = now_turn;
+    return Ok(board_info);
+}
+
+// 手番側の盤面と相手側の盤面からBoardInfoを作る（手番の色は分からないので黒とする）
+// 盤面が重なっていたり，石が初期局面より少なかったりすればエラーを返す
+pub fn boards_to_board_info(player_board: u64, opponent_board: u64) -> Result<BoardInfo, String> {
+    if player_board & opponent_board != 0 {
+        return Err(String::from("boards overlap"));
+    }
+    let discs = (player_board | opponent_board).count_ones() as i8;
+    if discs < 4 {
+        return Err(format!("only {} discs on the board", discs));
+    }
+    return Ok(BoardInfo {
+        now_turn: BLACK,
+        now_index: discs - 3,
+        player_board,
+        opponent_board,
+    });
+}
+
+// char2つによる文字の入力に対応する場所のビットを立てた盤面を返す
+pub fn point_to_bit(inp1: char, inp2: char) -> u64 {
+    let mut ret: u64 = 0x8000000000000000;
+    match inp1 {
+        'A' => (),
+        'B' => ret = ret >> 1,
+        'C' => ret = ret >> 2,
+        'D' => ret = ret >> 3,
+        'E' => ret = ret >> 4,
+        'F' => ret = ret >> 5,
+        'G' => ret = ret >> 6,
+        'H' => ret = ret >> 7,
+         _  => (),
+    }
+    let tmp: i32 = (inp2 as i32) - 48;
+    ret = ret >> ((tmp-1)*8);
+
+    return ret;
+}
+
+// ビットが1つだけ立っているu64の場所をchar2つに変換する
+pub fn bit_to_point(num: u64) -> (char, char) {
+    let mut count = 0;
+    let mut num_cpy = num;
+    while num_cpy != 0x8000000000000000 {
+        count = count + 1;
+        num_cpy = num_cpy << 1;
+    }
+    let c1: char = match count % LINESIZE {
+        0 => 'A',
+        1 => 'B',
+        2 => 'C',
+        3 => 'D',
+        4 => 'E',
+        5 => 'F',
+        6 => 'G',
+        7 => 'H',
+        _ => panic!("invalid rem"),
+    };
+    let c2: char = std::char::from_digit(((count / LINESIZE) + 1) as u32, 10).unwrap();
+    return (c1, c2);
+}
+
+// 打った手を"F5"や"PASS"のような文字列にする
+pub fn move_to_string(place_bit: u64) -> String {
+    if place_bit == 0 {
+        return "PASS".to_string();
+    }
+    let (c1, c2) = bit_to_point(place_bit);
+    return format!("{}{}", c1, c2);
+}
+
+// 手順を"F5 D6 C3"のような文字列にする
+pub fn line_to_string(line: &[u64]) -> String {
+    let moves: Vec<String> = line.iter().map(|&place_bit| move_to_string(place_bit)).collect();
+    return moves.join(" ");
+}
+
+// "F5"や"f5"，"PASS"のような文字列を打つ手にする（move_to_stringの逆）
+pub fn string_to_move(s: &str) -> Result<u64, String> {
+    if s.eq_ignore_ascii_case("PASS") {
+        return Ok(0);
+    }
+    let chars: Vec<char> = s.chars().collect();
+    if chars.len() != 2 || !('A'..='H').contains(&chars[0].to_ascii_uppercase()) || !('1'..='8').contains(&chars[1]) {
+        return Err(format!("invalid move: {}", s));
+    }
+    return Ok(point_to_bit(chars[0].to_ascii_uppercase(), chars[1]));
+}
+
+// "F5 D6 C3"のような空白区切りの文字列を手順にする（line_to_stringの逆）
+pub fn string_to_line(s: &str) -> Result<Vec<u64>, String> {
+    return s.split_whitespace().map(string_to_move).collect();
+}
+
+// player_boardからみておけるマスにフラグが立っている盤面を返す
+pub fn make_legal_board(board_info: &BoardInfo) -> u64 {
+    let horizontal_side: u64 = board_info.opponent_board & 0x7e7e7e7e7e7e7e7e;
+    let vertical_side: u64 = board_info.opponent_board & 0x00FFFFFFFFFFFF00;
+    let all_side: u64 = board_info.opponent_board & 0x007e7e7e7e7e7e00;
+    let blank_board: u64 = !(board_info.player_board | board_info.opponent_board);
+    let mut tmp: u64;
+    let mut legal_board: u64;
+
+    // 左側から挟めるビットを立てる
+    tmp = horizontal_side & (board_info.player_board << 1);
+    for _ in 0..5 {
+        tmp |= horizontal_side & (tmp << 1)
+    }
+    legal_board = blank_board & (tmp << 1);
+
+    // 右
+    tmp = horizontal_side & (board_info.player_board >> 1);
+    for _ in 0..5 {
+        tmp |= horizontal_side & (tmp >> 1)
+    }
+    legal_board |= blank_board & (tmp >> 1);
+
+    // 上
+    tmp = vertical_side & (board_info.player_board << 8);
+    for _ in 0..5 {
+        tmp |= vertical_side & (tmp << 8)
+    }
+    legal_board |= blank_board & (tmp << 8);
+
+    // 下
+    tmp = vertical_side & (board_info.player_board >> 8);
+    for _ in 0..5 {
+        tmp |= vertical_side & (tmp >> 8)
+    }
+    legal_board |= blank_board & (tmp >> 8);
+
+    // 右上
+    tmp = all_side & (board_info.player_board << 7);
+    for _ in 0..5 {
+        tmp |= all_side & (tmp << 7)
+    }
+    legal_board |= blank_board & (tmp << 7);
+
+    // 左上
+    tmp = all_side & (board_info.player_board << 9);
+    for _ in 0..5 {
+        tmp |= all_side & (tmp << 9)
+    }
+    legal_board |= blank_board & (tmp << 9);
+
+    // 右下
+    tmp = all_side & (board_info.player_board >> 9);
+    for _ in 0..5 {
+        tmp |= all_side & (tmp >> 9)
+    }
+    legal_board |= blank_board & (tmp >> 9);
+
+    // 左下
+    tmp = all_side & (board_info.player_board >> 7);
+    for _ in 0..5 {
+        tmp |= all_side & (tmp >> 7)
+    }
+    legal_board |= blank_board & (tmp >> 7);
+
+    return legal_board;
+}
+
+// 盤面が埋まっている直線（shiftだけずらした向きとその逆向き）上のマスにフラグが立っている盤面を返す
+// wall_forward，wall_backwardはそれぞれ>>shift，<<shiftした先が盤外になるマス
+fn make_filled_line_board(occupied: u64, shift: u32, wall_forward: u64, wall_backward: u64) -> u64 {
+    // forward: >>shiftの向きに盤端まで埋まっている，backward: <<shiftの向きに盤端まで埋まっている
+    let mut forward = occupied & wall_forward;
+    let mut backward = occupied & wall_backward;
+    for _ in 0..7 {
+        forward = occupied & (wall_forward | (forward << shift));
+        backward = occupied & (wall_backward | (backward >> shift));
+    }
+    return forward & backward;
+}
+
+// 確定石（以後どのように打たれても返されることのない石）にフラグが立っている盤面を(player_board側, opponent_board側)で返す
+// 横・縦・2つの斜めの4つの直線それぞれについて，その直線が埋まっているか，隣が盤外か同じ色の確定石であれば，その直線では返されない
+// 4つの直線すべてで返されない石を確定石とし，確定石が増えなくなるまで繰り返す
+pub fn make_stable_boards(board_info: &BoardInfo) -> (u64, u64) {
+    let occupied = board_info.player_board | board_info.opponent_board;
+    // (ずらす量, >>した先が盤外になるマス, <<した先が盤外になるマス)
+    let lines: [(u32, u64, u64); 4] = [
+        (1, 0x0101010101010101, 0x8080808080808080), // 横
+        (8, 0x00000000000000ff, 0xff00000000000000), // 縦
+        (9, 0x01010101010101ff, 0xff80808080808080), // 右下から左上
+        (7, 0x80808080808080ff, 0xff01010101010101), // 左下から右上
+    ];
+    let mut filled = [0u64; 4];
+    for (i, &(shift, wall_forward, wall_backward)) in lines.iter().enumerate() {
+        filled[i] = make_filled_line_board(occupied, shift, wall_forward, wall_backward) | wall_forward | wall_backward;
+    }
+
+    let mut stable_boards = [0u64; 2];
+    for (stable, own) in stable_boards.iter_mut().zip([board_info.player_board, board_info.opponent_board].iter()) {
+        loop {
+            let mut new_stable = *own;
+            for (i, &(shift, _, _)) in lines.iter().enumerate() {
+                // 盤外のマスからずれてきたビットは，そのマスがfilledに含まれるので影響しない
+                new_stable &= filled[i] | (*stable << shift) | (*stable >> shift);
+            }
+            if new_stable == *stable {
+                break;
+            }
+            *stable = new_stable;
+        }
+    }
+    return (stable_boards[0], stable_boards[1]);
+}
+
+// ビットを指定した方向に一つ動かす，ただし端は除かれる
+pub fn transfer(place_bit: &u64, direc: &i8) -> u64 {
+    return match direc {
+        0 => (place_bit << 8) & 0xffffffffffffff00, // 上
+        1 => (place_bit << 7) & 0x7f7f7f7f7f7f7f00, // 右上
+        2 => (place_bit >> 1) & 0x7f7f7f7f7f7f7f7f, // 右
+        3 => (place_bit >> 9) & 0x007f7f7f7f7f7f7f, // 右下
+        4 => (place_bit >> 8) & 0x00ffffffffffffff, // 下
+        5 => (place_bit >> 7) & 0x00fefefefefefefe, // 左下
+        6 => (place_bit << 1) & 0xfefefefefefefefe, // 左
+        7 => (place_bit << 9) & 0xfefefefefefefe00, // 左上
+        _ => panic!("transfer error."),
+    };
+}
+
+// playerがplace_bitに石をうったときに裏返る石にフラグが立っている盤面を返す（置けないマスなら0）
+pub fn make_reverse_board(place_bit: u64, board_info: &BoardInfo) -> u64 {
+    // (ずらす量, 端を除いた相手の石)，make_legal_boardと同じく盤端をまたいで続かないようにする
+    let directions: [(u32, u64); 4] = [
+        (1, board_info.opponent_board & 0x7e7e7e7e7e7e7e7e), // 左右
+        (8, board_info.opponent_board & 0x00FFFFFFFFFFFF00), // 上下
+        (7, board_info.opponent_board & 0x007e7e7e7e7e7e00), // 右上・左下
+        (9, board_info.opponent_board & 0x007e7e7e7e7e7e00), // 左上・右下
+    ];
+    let mut rev: u64 = 0;
+    for &(shift, side) in directions.iter() {
+        // 相手の石が続く限りrevの候補となるrev_subを伸ばし，その先が自分の石なら挟めている
+        let mut rev_sub: u64 = side & (place_bit << shift);
+        for _ in 0..5 {
+            rev_sub |= side & (rev_sub << shift);
+        }
+        if board_info.player_board & (rev_sub << shift) != 0 {
+            rev |= rev_sub;
+        }
+
+        rev_sub = side & (place_bit >> shift);
+        for _ in 0..5 {
+            rev_sub |= side & (rev_sub >> shift);
+        }
+        if board_info.player_board & (rev_sub >> shift) != 0 {
+            rev |= rev_sub;
+        }
+    }
+    return rev;
+}
+
+// playerが石をうつ部分の処理
+pub fn place(place_bit: u64, board_info: &mut BoardInfo) -> i8 {
+    
+    let legal_board: u64 = make_legal_board(&board_info);
+
+    // パス
+    if place_bit == 0 as u64 {
+        if legal_board == 0 as u64 { // 本当に着手可能な手がないならパス
+            // 相手側もパスなら終局
+            let tmp_board_info: BoardInfo = BoardInfo {
+                now_turn: board_info.now_turn.clone() * TURN_CHANGE_FACTOR,
+                now_index: board_info.now_index.clone(),
+                player_board: board_info.opponent_board.clone(),
+                opponent_board: board_info.player_board.clone(),
+            };
+            if make_legal_board(&tmp_board_info) == 0 { // 相手側もパスの場合終局
+                return GAME_SET;
+            }
+
+            return CONTINUE;
+        }else{ // おける場所があるのにパスすることはできない
+            return PLACE_ERR;
+        }
+    }
+
+    // 石をうつ
+    if (place_bit & legal_board) == place_bit { // 着手可能なら処理を続ける
+        // 石を裏返す処理
+        let rev: u64 = make_reverse_board(place_bit, board_info); // 裏返す部分のビットを立てる変数
+        board_info.player_board ^= place_bit | rev; // XORをとる
+        board_info.opponent_board ^= rev;
+        // 石を裏返す処理終了
+
+        board_info.now_index += 1;
+        // 60手目を打ち終わったら終局
+        if board_info.now_index > 60 {
+            return GAME_SET;
+        }
+    }else{ // 着手可能でない
+        return PLACE_ERR;
+    };
+
+    return CONTINUE;
+
+}
+
+// 終局かどうかを判定
+pub fn is_game_over(board_info: &BoardInfo) -> bool {
+    let player_legal_board = make_legal_board(board_info);
+    let mut tmp_board_info = board_info.clone();
+    swap(&mut tmp_board_info); // 相手側から見た合法手を調べる
+    let opponent_legal_board = make_legal_board(&tmp_board_info);
+    return player_legal_board == 0 as u64 && opponent_legal_board == 0 as u64;
+}
+
+// 手番入れ替え
+pub fn swap(board_info: &mut BoardInfo) {
+    let tmp: u64 = board_info.player_board;
+    board_info.player_board = board_info.opponent_board;
+    board_info.opponent_board = tmp;
+    board_info.now_turn *= TURN_CHANGE_FACTOR;
+}
+
+// 結果取得, 返り値は(黒コマ数，白コマ数，優勢)
+pub fn get_result(board_info: &BoardInfo) -> (u32, u32, i8) {
+    let mut black_count: u32 = board_info.player_board.count_ones();
+    let mut white_count: u32 = board_info.opponent_board.count_ones();
+    if board_info.now_turn == WHITE {
+        let tmp = black_count;
+        black_count = white_count;
+        white_count = tmp;
+    }
+    let mut superior: i8 = BLACK;
+    if black_count <= white_count {
+        if black_count == white_count {
+            superior = NONE;
+        }else{
+            superior = WHITE;
+        }
+    }
+    return (black_count, white_count, superior);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::random::{random_game, Rng};
+
+    // (行, 列)のマスのビット（A1が(0, 0)）
+    fn square(row: i32, col: i32) -> u64 {
+        return 1u64 << (63 - (row * 8 + col));
+    }
+
+    fn on_board(row: i32, col: i32) -> bool {
+        return (0..8).contains(&row) && (0..8).contains(&col);
+    }
+
+    // make_stable_boardsと同じ規則を1マスずつ愚直に調べたもの
+    fn brute_force_stable(own: u64, occupied: u64) -> u64 {
+        let directions = [(0, 1), (1, 0), (1, 1), (1, -1)];
+        let mut stable: u64 = 0;
+        loop {
+            let mut new_stable = stable;
+            for row in 0..8 {
+                for col in 0..8 {
+                    if own & square(row, col) == 0 {
+                        continue;
+                    }
+                    let is_stable = directions.iter().all(|&(dr, dc)| {
+                        let mut filled = true;
+                        for &sign in [1, -1].iter() {
+                            let (mut r, mut c) = (row + dr * sign, col + dc * sign);
+                            while on_board(r, c) {
+                                filled &= occupied & square(r, c) != 0;
+                                r += dr * sign;
+                                c += dc * sign;
+                            }
+                        }
+                        let neighbor_safe = [1, -1].iter().any(|&sign| {
+                            let (r, c) = (row + dr * sign, col + dc * sign);
+                            return !on_board(r, c) || stable & square(r, c) != 0;
+                        });
+                        return filled || neighbor_safe;
+                    });
+                    if is_stable {
+                        new_stable |= square(row, col);
+                    }
+                }
+            }
+            if new_stable == stable {
+                return stable;
+            }
+            stable = new_stable;
+        }
+    }
+
+    #[test]
+    fn stable_boards_match_brute_force() {
+        let mut rng = Rng::new(16);
+        for _ in 0..50 {
+            for board_info in random_game(&mut rng).0.iter() {
+                let occupied = board_info.player_board | board_info.opponent_board;
+                let (player_stable, opponent_stable) = make_stable_boards(board_info);
+                assert_eq!(player_stable, brute_force_stable(board_info.player_board, occupied), "{}", board_info_to_string(board_info));
+                assert_eq!(opponent_stable, brute_force_stable(board_info.opponent_board, occupied), "{}", board_info_to_string(board_info));
+            }
+        }
+    }
+
+    #[test]
+    fn stable_discs_are_never_flipped() {
+        let mut rng = Rng::new(61);
+        for _ in 0..50 {
+            let (positions, _) = random_game(&mut rng);
+            let last = positions.last().unwrap();
+            for board_info in positions.iter() {
+                let (player_stable, opponent_stable) = make_stable_boards(board_info);
+                // 終局の盤面で，同じ色の石のままであること
+                let (own, other) = if board_info.now_turn == last.now_turn {
+                    (last.player_board, last.opponent_board)
+                }else{
+                    (last.opponent_board, last.player_board)
+                };
+                assert_eq!(player_stable & !own, 0, "{}", board_info_to_string(board_info));
+                assert_eq!(opponent_stable & !other, 0, "{}", board_info_to_string(board_info));
+            }
+        }
+    }
+
+    #[test]
+    fn filled_edge_is_stable() {
+        let mut board_info = BoardInfo::new();
+        board_info.player_board = 0xff00000000000000; // 1行目をすべて自分の石にする
+        board_info.opponent_board = 0;
+        let (player_stable, _) = make_stable_boards(&board_info);
+        assert_eq!(player_stable, 0xff00000000000000);
+    }
+
+    #[test]
+    fn position_string_round_trip() {
+        let initial = BoardInfo::new();
+        let s = board_info_to_string(&initial);
+        assert_eq!(s, "---------------------------OX------XO--------------------------- X");
+
+        let mut rng = Rng::new(24);
+        for _ in 0..20 {
+            for board_info in random_game(&mut rng).0.iter() {
+                let s = board_info_to_string(board_info);
+                let parsed = string_to_board_info(&s).unwrap();
+                assert_eq!(parsed.now_turn, board_info.now_turn, "{}", s);
+                assert_eq!(parsed.now_index, board_info.now_index, "{}", s);
+                assert_eq!((parsed.player_board, parsed.opponent_board), (board_info.player_board, board_info.opponent_board), "{}", s);
+                assert_eq!(board_info_to_string(&parsed), s);
+            }
+        }
+    }
+
+    #[test]
+    fn string_to_board_info_accepts_other_spellings() {
+        let s = "-------- -------- -------- ---ox--- ---*o... -------- -------- -------- o ; white to move";
+        let board_info = string_to_board_info(s).unwrap();
+        assert_eq!(board_info.now_turn, WHITE);
+        assert_eq!(board_info_to_string(&board_info), "---------------------------OX------XO--------------------------- O");
+    }
+
+    #[test]
+    fn string_to_board_info_rejects_bad_strings() {
+        assert!(string_to_board_info("").is_err());
+        assert!(string_to_board_info("---------------------------OX------XO---------------------------").is_err()); // 手番がない
+        assert!(string_to_board_info("---------------------------OX------XO--------------------------- -").is_err());
+        assert!(string_to_board_info("---------------------------OZ------XO--------------------------- X").is_err());
+        assert!(string_to_board_info("---------------------------OX------XO---------------------------- X").is_err());
+        assert!(string_to_board_info("---------------------------------------------------------------- X").is_err()); // 石がない
+        assert!(string_to_board_info("---------------------------OX------X---------------------------- O").is_err()); // 初期局面より石が少ない
+    }
+}
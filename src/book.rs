@@ -0,0 +1,482 @@
+// 定石（opening book）
+// 局面ごとに，定石として打つ手とその重み（その手を含む定石の手順の数），評価値をもつ
+// 局面は盤面の8通りの対称変換のうち最小のもの（正規形）で引くので，対称な局面は同じものとして扱う
+// 定石の末端の局面を探索して評価値をつけ，それをnegamaxで根の方へ伝えながら定石を広げていくこともできる（build）
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fs;
+
+use crate::board::*;
+use crate::search::*;
+use crate::random::Rng;
+use crate::symmetry::*;
+
+// 定石の手の選び方の既定値（Book::choose()を参照）
+pub const BOOK_RANDOMNESS: i32 = 0;
+
+fn max(a: i32, b: i32) -> i32 {
+    return if a < b {b} else {a};
+}
+
+// 定石の1手
+#[derive(Clone, Debug)]
+pub struct BookMove {
+    pub place_bit: u64,     // 打つ手（正規形の局面での位置）
+    pub weight: u32,        // 重み（この手を含む定石の手順の数）
+    pub score: Option<i32>, // 打った側からみた評価値（中盤の評価関数の単位），まだ求めていなければNone
+}
+
+pub struct Book {
+    positions: HashMap<(u64, u64), Vec<BookMove>>, // 正規形の局面 -> 定石の手
+}
+
+impl Book {
+    // 空の定石を作る
+    pub fn new() -> Book {
+        return Book {
+            positions: HashMap::new(),
+        };
+    }
+
+    // 定石に含まれる局面の数
+    pub fn len(&self) -> usize {
+        return self.positions.len();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.positions.is_empty();
+    }
+
+    // board_infoの局面でplace_bitを打つ手の重みをweightだけ増やす（なければ加える）
+    pub fn add_move(&mut self, board_info: &BoardInfo, place_bit: u64, weight: u32) {
+        self.entry(board_info, place_bit).weight += weight;
+    }
+
+    // board_infoの局面のplace_bitの手（なければ重み0で加える）
+    fn entry(&mut self, board_info: &BoardInfo, place_bit: u64) -> &mut BookMove {
+        let (key, _) = canonical(board_info);
+        let place_bit = canonical_move(board_info, place_bit); // 対称な手は同じものとして扱う
+        let moves = self.positions.entry(key).or_default();
+        let index = match moves.iter().position(|m| m.place_bit == place_bit) {
+            Some(index) => index,
+            None => {
+                moves.push(BookMove {
+                    place_bit,
+                    weight: 0,
+                    score: None,
+                });
+                moves.len() - 1
+            },
+        };
+        return &mut moves[index];
+    }
+
+    pub fn add_line(&mut self, line: &[u64]) -> Result<(), String> {
+        let mut board_info = BoardInfo::new();
+        for &place_bit in line.iter() {
+            if make_legal_board(&board_info) == 0 && place_bit != 0 && !is_game_over(&board_info) {
+                swap(&mut board_info);
+            }
+            if place_bit == 0 {
+                if place(0, &mut board_info) == PLACE_ERR {
+                    return Err("PASS is not allowed here".to_string());
+                }
+                swap(&mut board_info);
+                continue;
+            }
+            let before = board_info.clone();
+            if place(place_bit, &mut board_info) == PLACE_ERR {
+                return Err(format!("{} is not a legal move", move_to_string(place_bit)));
+            }
+            swap(&mut board_info);
+            self.add_move(&before, place_bit, 1);
+        }
+        return Ok(());
+    }
+
+    // board_infoの局面での定石の手（実際の局面での位置にしたもの），定石になければ空
+    pub fn lookup(&self, board_info: &BoardInfo) -> Vec<BookMove> {
+        let (key, k) = canonical(board_info);
+        return match self.positions.get(&key) {
+            Some(moves) => moves.iter().map(|m| BookMove {
+                place_bit: inverse_transform(k, m.place_bit),
+                weight: m.weight,
+                score: m.score,
+            }).collect(),
+            None => Vec::new(),
+        };
+    }
+
+    // board_infoの局面で定石の手を1つ選ぶ，定石になければNone
+    // 評価値のついた手があれば，最善の評価値からrandomness（0〜100，評価値の単位）以内の手から重みに比例した確率で選ぶ
+    // 評価値がなければ，randomnessが0なら最も重みの大きい手，そうでなければ重みが最大の(100-randomness)%以上の手から重みに比例した確率で選ぶ
+    pub fn choose(&self, board_info: &BoardInfo, randomness: i32, rng: &mut Rng) -> Option<u64> {
+        let legal_board = make_legal_board(board_info);
+        let moves: Vec<BookMove> = self.lookup(board_info).into_iter().filter(|m| m.place_bit & legal_board != 0 && m.weight > 0).collect();
+        let candidates: Vec<&BookMove> = match moves.iter().filter_map(|m| m.score).max() {
+            Some(best_score) => moves.iter().filter(|m| m.score.is_some_and(|score| score >= best_score - randomness)).collect(),
+            None => {
+                let max_weight = moves.iter().map(|m| m.weight).max()?;
+                let threshold = max_weight as u64 * (100 - randomness) as u64;
+                moves.iter().filter(|m| m.weight as u64 * 100 >= threshold).collect()
+            },
+        };
+        if candidates.len() == 1 {
+            return Some(candidates[0].place_bit);
+        }
+        let total: u64 = candidates.iter().map(|m| m.weight as u64).sum();
+        let mut n = rng.next_u64() % total;
+        for m in candidates.iter() {
+            if n < m.weight as u64 {
+                return Some(m.place_bit);
+            }
+            n -= m.weight as u64;
+        }
+        return None;
+    }
+
+    // 定石の手順のリスト（1行に1つ，"F5 D6 C3"のような形，#以降はコメント）から定石を作る
+    pub fn from_lines(text: &str) -> Result<Book, String> {
+        let mut book = Book::new();
+        for (i, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap().trim();
+            if line.is_empty() {
+                continue;
+            }
+            let moves = string_to_line(line).map_err(|e| format!("line {}: {}", i + 1, e))?;
+            book.add_line(&moves).map_err(|e| format!("line {}: {}", i + 1, e))?;
+        }
+        return Ok(book);
+    }
+
+    // テキストから定石を読み込む
+    // 書式は1行に「手番側の盤面 相手側の盤面 手 重み [評価値]」（盤面は16進数，手はパスならPASS，評価値は省略できる），#以降はコメント
+    // 正規形でない局面は正規形に直して読み込み（同じ局面の同じ手は重みを足し合わせる），合法手でない手があればエラーを返す
+    pub fn parse(text: &str) -> Result<Book, String> {
+        let mut book = Book::new();
+        for (i, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap().trim();
+            if line.is_empty() {
+                continue;
+            }
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if fields.len() != 4 && fields.len() != 5 {
+                return Err(format!("line {}: expected 4 or 5 fields", i + 1));
+            }
+            let player_board = u64::from_str_radix(fields[0], 16).map_err(|_| format!("line {}: invalid board", i + 1))?;
+            let opponent_board = u64::from_str_radix(fields[1], 16).map_err(|_| format!("line {}: invalid board", i + 1))?;
+            let place_bit = string_to_move(fields[2]).map_err(|e| format!("line {}: {}", i + 1, e))?;
+            let weight: u32 = fields[3].parse().map_err(|_| format!("line {}: invalid weight", i + 1))?;
+            let score: Option<i32> = match fields.get(4) {
+                Some(field) => Some(field.parse().map_err(|_| format!("line {}: invalid score", i + 1))?),
+                None => None,
+            };
+            let board_info = boards_to_board_info(player_board, opponent_board).map_err(|e| format!("line {}: {}", i + 1, e))?;
+            // パス（PASS）は打てる手がなく，終局していない局面でだけ打てる（expand()がパスしかない局面で加える）
+            let legal_board = make_legal_board(&board_info);
+            let is_legal = if place_bit == 0 {legal_board == 0 && !is_game_over(&board_info)} else {place_bit & legal_board != 0};
+            if !is_legal {
+                return Err(format!("line {}: {} is not a legal move", i + 1, move_to_string(place_bit)));
+            }
+            let m = book.entry(&board_info, place_bit);
+            m.weight += weight;
+            if score.is_some() {
+                m.score = score;
+            }
+        }
+        return Ok(book);
+    }
+
+    // ファイルから定石を読み込む
+    pub fn load(path: &str) -> Result<Book, String> {
+        let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+        return Book::parse(&text).map_err(|e| format!("{}: {}", path, e));
+    }
+
+    // parse()で読み込める形式のテキストにする（局面の順に並べる）
+    pub fn to_text(&self) -> String {
+        let mut keys: Vec<&(u64, u64)> = self.positions.keys().collect();
+        keys.sort();
+        let mut text = String::from("# player opponent move weight [score]\n");
+        for key in keys {
+            for m in self.positions[key].iter() {
+                text += &format!("{:016x} {:016x} {} {}", key.0, key.1, move_to_string(m.place_bit), m.weight);
+                if let Some(score) = m.score {
+                    text += &format!(" {}", score);
+                }
+                text += "\n";
+            }
+        }
+        return text;
+    }
+
+    // 評価値のない手のうち，打ったあとの局面が定石にないもの（末端）を深さdepthで探索して評価値をつける，返り値はつけた数
+    pub fn evaluate_leaves(&mut self, depth: i8, shared: &SearchShared) -> usize {
+        let mut count = 0;
+        let keys: Vec<(u64, u64)> = self.positions.keys().cloned().collect();
+        for key in keys {
+            for i in 0..self.positions[&key].len() {
+                let m = &self.positions[&key][i];
+                if m.score.is_some() {
+                    continue;
+                }
+                let child = play(key, m.place_bit);
+                if self.positions.contains_key(&canonical(&child).0) { // propagate()で決まる
+                    continue;
+                }
+                let score = -search_score(&child, depth, shared);
+                self.positions.get_mut(&key).unwrap()[i].score = Some(score);
+                count += 1;
+            }
+        }
+        return count;
+    }
+
+    // 末端の評価値をnegamaxで根の方へ伝え，打ったあとの局面が定石にある手の評価値を，その局面の最善の評価値にする
+    pub fn propagate(&mut self) {
+        let mut values = HashMap::<(u64, u64), Option<i32>>::new();
+        let keys: Vec<(u64, u64)> = self.positions.keys().cloned().collect();
+        for key in keys {
+            self.propagate_from(key, &mut values);
+        }
+    }
+
+    // 局面keyの手番側からみた評価値（定石の手の評価値の最大値）を求める，valuesは求めた局面の評価値
+    fn propagate_from(&mut self, key: (u64, u64), values: &mut HashMap<(u64, u64), Option<i32>>) -> Option<i32> {
+        if let Some(&value) = values.get(&key) {
+            return value;
+        }
+        let mut best: Option<i32> = None;
+        for i in 0..self.positions[&key].len() {
+            let m = &self.positions[&key][i];
+            let (child_key, _) = canonical(&play(key, m.place_bit));
+            let mut score = m.score;
+            if self.positions.contains_key(&child_key) {
+                score = self.propagate_from(child_key, values).map(|value| -value);
+                self.positions.get_mut(&key).unwrap()[i].score = score;
+            }
+            if let Some(score) = score {
+                best = Some(best.map_or(score, |b| max(b, score)));
+            }
+        }
+        values.insert(key, best);
+        return best;
+    }
+
+    // 初期局面から，各局面で最善の評価値からmargin以内の手だけをたどり，まだ定石にない局面のうち最も浅いもの（max_plies手目まで）を返す
+    fn find_leaf(&self, max_plies: i32, margin: i32) -> Option<BoardInfo> {
+        let mut queue = VecDeque::<(BoardInfo, i32)>::new();
+        let mut visited = HashSet::<(u64, u64)>::new();
+        queue.push_back((BoardInfo::new(), 0));
+        while let Some((board_info, plies)) = queue.pop_front() {
+            let (key, _) = canonical(&board_info);
+            if plies > max_plies || !visited.insert(key) {
+                continue;
+            }
+            let moves = match self.positions.get(&key) {
+                Some(moves) => moves,
+                None => {
+                    if !is_game_over(&board_info) {
+                        return Some(board_info);
+                    }
+                    continue;
+                },
+            };
+            let best_score = moves.iter().filter_map(|m| m.score).max();
+            for m in moves.iter() {
+                let playable = match (m.score, best_score) {
+                    (Some(score), Some(best_score)) => score >= best_score - margin,
+                    _ => true,
+                };
+                if playable {
+                    queue.push_back((play(key, m.place_bit), plies + 1));
+                }
+            }
+        }
+        return None;
+    }
+
+    // find_leaf()の局面の全合法手を深さdepthで探索し，最善の評価値からmargin以内の手を評価値つきで定石に加える
+    // 返り値は加えた局面（広げられる局面がなければNone）
+    pub fn expand(&mut self, depth: i8, max_plies: i32, margin: i32, shared: &SearchShared) -> Option<BoardInfo> {
+        let board_info = self.find_leaf(max_plies, margin)?;
+        let mut legal_board = make_legal_board(&board_info);
+        let mut scores = Vec::<(u64, i32)>::new();
+        if legal_board == 0 { // パスしかできない
+            let mut tmp_board_info = board_info.clone();
+            swap(&mut tmp_board_info);
+            scores.push((0, -search_score(&tmp_board_info, depth, shared)));
+        }
+        while legal_board != 0 {
+            let mask = legal_board & legal_board.wrapping_neg();
+            legal_board ^= mask;
+            let mut tmp_board_info = board_info.clone();
+            place(mask, &mut tmp_board_info);
+            swap(&mut tmp_board_info);
+            scores.push((mask, -search_score(&tmp_board_info, depth, shared)));
+        }
+
+        let best_score = scores.iter().map(|&(_, score)| score).max().unwrap();
+        for &(mask, score) in scores.iter() {
+            if score < best_score - margin {
+                continue;
+            }
+            let m = self.entry(&board_info, mask); // 対称な手は1つにまとめる
+            m.weight = 1;
+            m.score = Some(score);
+        }
+        return Some(board_info);
+    }
+}
+
+// 正規形の局面keyでplace_bitに打ち，手番を相手に渡した局面
+// 定石の局面は読み込むときに確かめてあるので，keyは正しい盤面である
+fn play(key: (u64, u64), place_bit: u64) -> BoardInfo {
+    let mut board_info = boards_to_board_info(key.0, key.1).expect("book positions are valid boards");
+    place(place_bit, &mut board_info);
+    swap(&mut board_info);
+    return board_info;
+}
+
+// board_infoの局面を中盤の評価関数で深さdepthまで探索した，手番側からみた評価値
+fn search_score(board_info: &BoardInfo, depth: i8, shared: &SearchShared) -> i32 {
+    return shared.search(std::i32::MIN + 1, std::i32::MAX, depth, board_info, shared.engine.midgame_evaluator()).0;
+}
+
+impl Default for Book {
+    fn default() -> Self {
+        return Book::new();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use crate::engine::Engine;
+    use crate::random::random_game;
+
+    // 初期局面から手順lineを打った局面
+    fn position_after(line: &str) -> BoardInfo {
+        let mut board_info = BoardInfo::new();
+        for place_bit in string_to_line(line).unwrap() {
+            place(place_bit, &mut board_info);
+            swap(&mut board_info);
+        }
+        return board_info;
+    }
+
+    // board_infoの局面の定石の手（"F5"のような文字列，対称な手でもよい）の(重み，評価値)
+    fn book_move(book: &Book, board_info: &BoardInfo, place: &str) -> Option<(u32, Option<i32>)> {
+        let place_bit = canonical_move(board_info, string_to_move(place).unwrap());
+        return book.lookup(board_info).iter().find(|m| canonical_move(board_info, m.place_bit) == place_bit).map(|m| (m.weight, m.score));
+    }
+
+    #[test]
+    fn text_round_trip_keeps_weights_and_scores() {
+        let mut book = Book::from_lines("F5 D6 C3\nF5 F6\nF5 D6 C5 # comment\n").unwrap();
+        book.entry(&position_after("F5"), string_to_move("F6").unwrap()).score = Some(-12);
+        book.entry(&position_after("F5 D6"), string_to_move("C3").unwrap()).score = Some(4);
+
+        let text = book.to_text();
+        let parsed = Book::parse(&text).unwrap();
+        assert_eq!(parsed.to_text(), text);
+        assert_eq!(parsed.len(), 3);
+        assert_eq!(book_move(&parsed, &BoardInfo::new(), "F5"), Some((3, None)));
+        assert_eq!(book_move(&parsed, &position_after("F5"), "D6"), Some((2, None)));
+        assert_eq!(book_move(&parsed, &position_after("F5"), "F6"), Some((1, Some(-12))));
+        assert_eq!(book_move(&parsed, &position_after("F5 D6"), "C3"), Some((1, Some(4))));
+        // 対称な序盤の手も同じ手として引ける
+        assert_eq!(book_move(&parsed, &position_after("E6"), "F4"), Some((2, None)));
+    }
+
+    #[test]
+    fn parse_merges_symmetric_positions() {
+        let board_info = position_after("F5 D6");
+        let place_bit = string_to_move("C3").unwrap();
+        let mut text = String::new();
+        for k in 0..SYMMETRIES {
+            let score = if k == SYMMETRIES - 1 {" 7".to_string()} else {String::new()};
+            text += &format!("{:016x} {:016x} {} 1{}\n", transform(k, board_info.player_board), transform(k, board_info.opponent_board), move_to_string(transform(k, place_bit)), score);
+        }
+        let book = Book::parse(&text).unwrap();
+        assert_eq!(book.len(), 1);
+        let moves = book.lookup(&board_info);
+        assert_eq!(moves.len(), 1);
+        assert_eq!((moves[0].place_bit, moves[0].weight, moves[0].score), (place_bit, SYMMETRIES as u32, Some(7)));
+        assert!(Book::parse("0000000000000000 0000000000000000 F5 1").is_err()); // 石がない
+        assert!(Book::parse("0000001008000000 0000000810000000 A1 1").is_err()); // 合法手でない
+    }
+
+    #[test]
+    fn choose_without_randomness_picks_the_best_move() {
+        let mut rng = Rng::new(18);
+        let board_info = position_after("F5 D6"); // 対称でない局面
+        // 評価値がなければ最も重みの大きい手
+        let mut book = Book::from_lines("F5 D6 C5\nF5 D6 C5\nF5 D6 C3\nF5 D6 C4").unwrap();
+        for _ in 0..10 {
+            assert_eq!(book.choose(&board_info, 0, &mut rng), Some(string_to_move("C5").unwrap()));
+        }
+        // 評価値があれば重みによらず評価値が最大の手
+        book.entry(&board_info, string_to_move("C5").unwrap()).score = Some(-5);
+        book.entry(&board_info, string_to_move("C3").unwrap()).score = Some(3);
+        book.entry(&board_info, string_to_move("C4").unwrap()).score = Some(-20);
+        for _ in 0..10 {
+            assert_eq!(book.choose(&board_info, 0, &mut rng), Some(string_to_move("C3").unwrap()));
+        }
+        assert_eq!(book.choose(&position_after("F5 F6"), 0, &mut rng), None); // 定石にない局面
+    }
+
+    #[test]
+    fn propagate_takes_the_negamax_of_the_leaves() {
+        // F5 -> {D6 -> C3, F6}，評価値は末端の手（C3とF6）にだけつける
+        let mut book = Book::from_lines("F5 D6 C3\nF5 F6").unwrap();
+        book.entry(&position_after("F5 D6"), string_to_move("C3").unwrap()).score = Some(50);
+        book.entry(&position_after("F5"), string_to_move("F6").unwrap()).score = Some(-10);
+        book.propagate();
+        assert_eq!(book_move(&book, &position_after("F5"), "D6"), Some((1, Some(-50))));
+        assert_eq!(book_move(&book, &position_after("F5"), "F6"), Some((1, Some(-10))));
+        assert_eq!(book_move(&book, &BoardInfo::new(), "F5"), Some((2, Some(10))));
+
+        // 末端の評価値が変われば，伝え直した値も変わる
+        book.entry(&position_after("F5 D6"), string_to_move("C3").unwrap()).score = Some(-30);
+        book.propagate();
+        assert_eq!(book_move(&book, &position_after("F5"), "D6"), Some((1, Some(30))));
+        assert_eq!(book_move(&book, &BoardInfo::new(), "F5"), Some((2, Some(-30))));
+    }
+
+    #[test]
+    fn expanded_pass_survives_a_round_trip() {
+        // パスを含む対局のパスの直前までを定石にし，パスしかない局面を広げさせる
+        let mut rng = Rng::new(19);
+        let (positions, moves) = loop {
+            let (positions, moves) = random_game(&mut rng);
+            if moves.contains(&0) {
+                break (positions, moves);
+            }
+        };
+        let pass_index = moves.iter().position(|&place_bit| place_bit == 0).unwrap();
+        let mut book = Book::new();
+        book.add_line(&moves[..pass_index]).unwrap();
+
+        let shared = SearchShared::new(&Arc::new(Engine::new()), 1);
+        let expanded = book.expand(1, BOARDSIZE, 0, &shared).unwrap();
+        assert_eq!(canonical(&expanded).0, canonical(&positions[pass_index]).0);
+        let pass = book.lookup(&expanded);
+        assert_eq!(pass.len(), 1);
+        assert_eq!((pass[0].place_bit, pass[0].weight), (0, 1));
+        assert!(pass[0].score.is_some());
+
+        let text = book.to_text();
+        assert!(text.contains(" PASS 1 "));
+        let parsed = Book::parse(&text).unwrap();
+        assert_eq!(parsed.to_text(), text);
+    }
+
+    #[test]
+    fn parse_rejects_a_pass_when_there_are_legal_moves() {
+        let board_info = BoardInfo::new();
+        let text = format!("{:016x} {:016x} PASS 1", board_info.player_board, board_info.opponent_board);
+        assert!(Book::parse(&text).is_err());
+    }
+}
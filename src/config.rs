@@ -0,0 +1,114 @@
+// 実行時の設定（eval_normalの重み，各評価関数で読む深さ，定石の手の選び方）
+// 設定ファイルやコマンドライン引数から読み込み，Engineに持たせて探索に用いる
+
+use std::fs;
+
+use crate::board::MAX_TURNS;
+use crate::book::BOOK_RANDOMNESS;
+use crate::eval::*;
+
+// 深さの設定の名前（Config::depthsの順）
+pub const DEPTH_NAMES: [&str; 5] = ["EVAL_BY_POINTTABLE_DEPTH", "EVAL_NORMAL_DEPTH", "EVAL_PERFECT_DEPTH", "EVAL_WIN_DEPTH", "EVAL_PATTERN_DEPTH"];
+const DEFAULT_DEPTHS: [i8; 5] = [EVAL_BY_POINTTABLE_DEPTH, EVAL_NORMAL_DEPTH, EVAL_PERFECT_DEPTH, EVAL_WIN_DEPTH, EVAL_PATTERN_DEPTH];
+
+#[derive(Clone, Debug)]
+pub struct Config {
+    pub weights: [i32; NORMAL_FEATURES], // eval_normalの重み（NORMAL_WEIGHT_NAMESの順）
+    pub depths: [i8; 5],                 // 各評価関数で読む深さ（DEPTH_NAMESの順）
+    pub book_randomness: i32,            // 定石の手の選び方（0〜100，Book::choose()を参照）
+}
+
+impl Config {
+    // コンパイル時の定数と同じ設定
+    pub fn new() -> Config {
+        return Config {
+            weights: DEFAULT_NORMAL_WEIGHTS,
+            depths: DEFAULT_DEPTHS,
+            book_randomness: BOOK_RANDOMNESS,
+        };
+    }
+
+    // nameの設定をvalueにする
+    pub fn set(&mut self, name: &str, value: &str) -> Result<(), String> {
+        if let Some(index) = NORMAL_WEIGHT_NAMES.iter().position(|&n| n == name) {
+            self.weights[index] = value.parse().map_err(|_| format!("invalid value for {}: {}", name, value))?;
+            return Ok(());
+        }
+        if let Some(index) = DEPTH_NAMES.iter().position(|&n| n == name) {
+            let depth: i8 = value.parse().map_err(|_| format!("invalid value for {}: {}", name, value))?;
+            if !(1..=MAX_TURNS).contains(&depth) {
+                return Err(format!("{} must be between 1 and {}", name, MAX_TURNS));
+            }
+            self.depths[index] = depth;
+            return Ok(());
+        }
+        if name == "BOOK_RANDOMNESS" {
+            let randomness: i32 = value.parse().map_err(|_| format!("invalid value for {}: {}", name, value))?;
+            if !(0..=100).contains(&randomness) {
+                return Err(format!("{} must be between 0 and 100", name));
+            }
+            self.book_randomness = randomness;
+            return Ok(());
+        }
+        return Err(format!("unknown setting {}", name));
+    }
+
+    // 「名前=値」の形の文字列で設定する（コマンドライン引数用）
+    pub fn set_assignment(&mut self, assignment: &str) -> Result<(), String> {
+        let mut parts = assignment.splitn(2, '=');
+        let name = parts.next().unwrap().trim();
+        let value = parts.next().ok_or(format!("expected NAME=VALUE: {}", assignment))?.trim();
+        return self.set(name, value);
+    }
+
+    // テキストから設定を読み込んで上書きする
+    // 書式は1行に「名前 値」，書かれていない設定はそのまま，#以降はコメント
+    pub fn parse(&mut self, text: &str) -> Result<(), String> {
+        for (i, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap().trim();
+            if line.is_empty() {
+                continue;
+            }
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if fields.len() != 2 {
+                return Err(format!("line {}: expected a name and a value", i + 1));
+            }
+            self.set(fields[0], fields[1]).map_err(|e| format!("line {}: {}", i + 1, e))?;
+        }
+        return Ok(());
+    }
+
+    // ファイルから設定を読み込んで上書きする
+    pub fn load(&mut self, path: &str) -> Result<(), String> {
+        let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+        return self.parse(&text).map_err(|e| format!("{}: {}", path, e));
+    }
+
+    // parse()で読み込める形式のテキストにする
+    pub fn to_text(&self) -> String {
+        let mut text = normal_weights_to_text(&self.weights);
+        for (name, depth) in DEPTH_NAMES.iter().zip(self.depths.iter()) {
+            text += &format!("{} {}\n", name, depth);
+        }
+        text += &format!("BOOK_RANDOMNESS {}\n", self.book_randomness);
+        return text;
+    }
+
+    // 評価関数nで読む深さ
+    pub fn depth(&self, n: i8) -> i8 {
+        return match n {
+            EVAL_BY_POINTTABLE => self.depths[0],
+            EVAL_NORMAL        => self.depths[1],
+            EVAL_PERFECT       => self.depths[2],
+            EVAL_WIN           => self.depths[3],
+            EVAL_PATTERN       => self.depths[4],
+            _                  => panic!("there is not such way of evaluation"),
+        };
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        return Config::new();
+    }
+}
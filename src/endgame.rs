@@ -0,0 +1,321 @@
+// 終盤の読み切り（EVAL_WIN，EVAL_PERFECT）専用の探索
+// 空きマスが少なくなったら，negamaxの代わりに置換表・並べ替えの表・スレッドの分担を使わない軽い探索で終局まで読む
+// 確定石による評価値の上限・下限での枝刈り，偶数理論（空きマスが奇数個の領域から打つ）による並べ替え，残り1〜4マス専用の処理を行う
+
+use crate::board::*;
+use crate::eval::*;
+use crate::search::SearchShared;
+
+// negamaxからsolve()に切り替える空きマス数
+pub const ENDGAME_SOLVER_EMPTIES: i32 = 8;
+// 速さ優先（相手の着手可能手数が少ない手を先に調べる）の並べ替えも行う空きマス数の下限
+const SOLVER_MOBILITY_MIN_EMPTIES: i32 = 7;
+// 合法手を求めずに空きマスを直接調べる，専用の処理に切り替える空きマス数
+const SOLVER_LAST_EMPTIES: i32 = 4;
+
+// 偶数理論の領域（盤面を4分割した各4x4）
+const QUADRANTS: [u64; 4] = [0xf0f0f0f000000000, 0x0f0f0f0f00000000, 0x00000000f0f0f0f0, 0x000000000f0f0f0f];
+
+fn max(a: i32, b: i32) -> i32 {
+    return if a < b {b} else {a};
+}
+
+// 最終石差を評価関数way_of_evalの評価値にする
+fn to_score(diff: i32, way_of_eval: i8) -> i32 {
+    return if way_of_eval == EVAL_WIN {diff.signum()} else {diff};
+}
+
+// 空きマスが奇数個の領域のマスにフラグが立っている盤面
+fn make_parity_board(empty_board: u64) -> u64 {
+    let mut parity_board: u64 = 0;
+    for &quadrant in QUADRANTS.iter() {
+        if (empty_board & quadrant).count_ones() % 2 == 1 {
+            parity_board |= quadrant;
+        }
+    }
+    return parity_board;
+}
+
+// place_bitに打って裏返し（revは裏返る石），手番を相手に渡した局面を返す
+fn make_child(board_info: &BoardInfo, place_bit: u64, rev: u64) -> BoardInfo {
+    return BoardInfo {
+        now_turn: board_info.now_turn * TURN_CHANGE_FACTOR,
+        now_index: board_info.now_index + 1,
+        player_board: board_info.opponent_board ^ rev,
+        opponent_board: board_info.player_board ^ (place_bit | rev),
+    };
+}
+
+// 確定石から分かる最終石差の上限・下限で枝刈りできれば，その評価値（way_of_evalの評価値）を返す
+// 相手の確定石は自分の石にならないので上限は64-2*(相手の確定石)，自分の確定石は残るので下限は2*(自分の確定石)-64
+pub fn stability_cutoff(alpha: i32, beta: i32, board_info: &BoardInfo, way_of_eval: i8) -> Option<i32> {
+    let player_count = board_info.player_board.count_ones() as i32;
+    let opponent_count = board_info.opponent_board.count_ones() as i32;
+    // 石の数から分かる上限・下限で枝刈りできる見込みがなければ，確定石は求めない
+    if to_score(BOARDSIZE - 2 * opponent_count, way_of_eval) > alpha && to_score(2 * player_count - BOARDSIZE, way_of_eval) < beta {
+        return None;
+    }
+
+    let (player_stable_board, opponent_stable_board) = make_stable_boards(board_info);
+    let upper = to_score(BOARDSIZE - 2 * opponent_stable_board.count_ones() as i32, way_of_eval);
+    if upper <= alpha {
+        return Some(upper);
+    }
+    let lower = to_score(2 * player_stable_board.count_ones() as i32 - BOARDSIZE, way_of_eval);
+    if lower >= beta {
+        return Some(lower);
+    }
+    return None;
+}
+
+// 読み切りの探索，返り値はway_of_eval（EVAL_WINかEVAL_PERFECT）の評価値
+// pvには読み筋が入る（残りSOLVER_LAST_EMPTIESマス以下の部分は入らない）
+pub fn solve(alpha: i32, beta: i32, board_info: &BoardInfo, way_of_eval: i8, shared: &SearchShared, pv: &mut Vec<u64>) -> i32 {
+    if way_of_eval == EVAL_WIN {
+        // 勝ち負けだけを知りたいので，最終石差について窓(-1, 1)の中で探索すれば十分
+        pv.clear();
+        if alpha >= 1 {
+            return 1;
+        }
+        if beta <= -1 {
+            return -1;
+        }
+        return solve_deep(max(alpha, -1), std::cmp::min(beta, 1), board_info, shared, pv).signum();
+    }
+    return solve_deep(alpha, beta, board_info, shared, pv);
+}
+
+// 最終石差を求める探索（SOLVER_LAST_EMPTIESマスより多いとき）
+fn solve_deep(alpha_: i32, beta: i32, board_info: &BoardInfo, shared: &SearchShared, pv: &mut Vec<u64>) -> i32 {
+    let mut alpha = alpha_;
+    pv.clear();
+
+    if shared.is_aborted() { // 探索打ち切り
+        return 0;
+    }
+
+    let empty_board = !(board_info.player_board | board_info.opponent_board);
+    let empties = empty_board.count_ones() as i32;
+    if empties <= SOLVER_LAST_EMPTIES {
+        return solve_last(alpha, beta, board_info, empty_board);
+    }
+
+    if let Some(score) = stability_cutoff(alpha, beta, board_info, EVAL_PERFECT) {
+        return score;
+    }
+
+    let legal_board = make_legal_board(board_info);
+    if legal_board == 0 { // パス
+        let mut tmp_board_info = board_info.clone();
+        swap(&mut tmp_board_info);
+        if make_legal_board(&tmp_board_info) == 0 { // 終局
+            return eval_perfect(board_info);
+        }
+        let score = -solve_deep(-beta, -alpha, &tmp_board_info, shared, pv);
+        pv.insert(0, 0);
+        return score;
+    }
+
+    // 空きマスが奇数個の領域の手を先に，その中では相手の着手可能手数が少ない手を先に調べる
+    let parity_board = make_parity_board(empty_board);
+    let mut moves = [(0u64, 0u64); 64]; // (打つ手，裏返る石)
+    let mut scores = [0i32; 64];
+    let mut count = 0;
+    let mut remaining = legal_board;
+    while remaining != 0 {
+        let mask = remaining & remaining.wrapping_neg();
+        remaining ^= mask;
+        let rev = make_reverse_board(mask, board_info);
+        let mut score = if mask & parity_board != 0 {1 << 8} else {0};
+        if empties >= SOLVER_MOBILITY_MIN_EMPTIES {
+            score -= make_legal_board(&make_child(board_info, mask, rev)).count_ones() as i32;
+        }
+        // 挿入ソート（得点の高い順）
+        let mut j = count;
+        while j > 0 && scores[j-1] < score {
+            scores[j] = scores[j-1];
+            moves[j] = moves[j-1];
+            j -= 1;
+        }
+        scores[j] = score;
+        moves[j] = (mask, rev);
+        count += 1;
+    }
+
+    let mut score_max = std::i32::MIN;
+    let mut child_pv: Vec<u64> = Vec::new();
+    for &(mask, rev) in moves[..count].iter() {
+        let score = -solve_deep(-beta, -alpha, &make_child(board_info, mask, rev), shared, &mut child_pv);
+        if score > score_max {
+            score_max = score;
+            pv.clear();
+            pv.push(mask);
+            pv.extend_from_slice(&child_pv);
+            if score >= beta { // βカット
+                return score;
+            }
+            alpha = max(alpha, score);
+        }
+    }
+    return score_max;
+}
+
+// 残り2〜4マスの探索，合法手を求めずに空きマスを偶数理論の順に直接調べる
+fn solve_last(alpha_: i32, beta: i32, board_info: &BoardInfo, empty_board: u64) -> i32 {
+    let mut alpha = alpha_;
+    if empty_board.count_ones() == 1 {
+        return solve_last1(board_info, empty_board);
+    }
+
+    let parity_board = make_parity_board(empty_board);
+    let mut score_max = std::i32::MIN;
+    for &candidates in [empty_board & parity_board, empty_board & !parity_board].iter() {
+        let mut remaining = candidates;
+        while remaining != 0 {
+            let mask = remaining & remaining.wrapping_neg();
+            remaining ^= mask;
+            let rev = make_reverse_board(mask, board_info);
+            if rev == 0 { // 置けない
+                continue;
+            }
+            let score = -solve_last(-beta, -alpha, &make_child(board_info, mask, rev), empty_board ^ mask);
+            if score > score_max {
+                score_max = score;
+                if score >= beta { // βカット
+                    return score;
+                }
+                alpha = max(alpha, score);
+            }
+        }
+    }
+
+    if score_max == std::i32::MIN { // パス
+        let mut tmp_board_info = board_info.clone();
+        swap(&mut tmp_board_info);
+        let mut remaining = empty_board;
+        while remaining != 0 {
+            let mask = remaining & remaining.wrapping_neg();
+            remaining ^= mask;
+            if make_reverse_board(mask, &tmp_board_info) != 0 { // 相手は置ける
+                return -solve_last(-beta, -alpha, &tmp_board_info, empty_board);
+            }
+        }
+        return eval_perfect(board_info); // 終局
+    }
+    return score_max;
+}
+
+// 残り1マスの最終石差
+fn solve_last1(board_info: &BoardInfo, empty_board: u64) -> i32 {
+    let diff = eval_perfect(board_info);
+    let rev = make_reverse_board(empty_board, board_info);
+    if rev != 0 {
+        return diff + 1 + 2 * rev.count_ones() as i32;
+    }
+    let mut tmp_board_info = board_info.clone();
+    swap(&mut tmp_board_info);
+    let rev = make_reverse_board(empty_board, &tmp_board_info);
+    if rev != 0 { // 相手が打つ
+        return diff - 1 - 2 * rev.count_ones() as i32;
+    }
+    return diff; // どちらも置けずに終局
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use crate::engine::Engine;
+    use crate::random::{random_game, Rng};
+
+    // 枝刈りをしないminimaxで求めた最終石差
+    fn minimax(board_info: &BoardInfo) -> i32 {
+        let legal_board = make_legal_board(board_info);
+        if legal_board == 0 {
+            let mut tmp_board_info = board_info.clone();
+            swap(&mut tmp_board_info);
+            if make_legal_board(&tmp_board_info) == 0 { // 終局
+                return eval_perfect(board_info);
+            }
+            return -minimax(&tmp_board_info);
+        }
+        let mut best = std::i32::MIN;
+        let mut remaining = legal_board;
+        while remaining != 0 {
+            let mask = remaining & remaining.wrapping_neg();
+            remaining ^= mask;
+            let mut tmp_board_info = board_info.clone();
+            place(mask, &mut tmp_board_info);
+            swap(&mut tmp_board_info);
+            best = max(best, -minimax(&tmp_board_info));
+        }
+        return best;
+    }
+
+    // ランダムに打って空きマスがempties個になった局面（その前に終局したらNone）
+    fn random_position(empties: u32, rng: &mut Rng) -> Option<BoardInfo> {
+        let (positions, _) = random_game(rng);
+        return positions.into_iter().find(|board_info| (board_info.player_board | board_info.opponent_board).count_zeros() == empties);
+    }
+
+    // 探索窓(alpha, beta)で求めたscoreが，真の値exactと矛盾しないこと
+    fn assert_within_window(score: i32, exact: i32, alpha: i32, beta: i32) {
+        if exact <= alpha {
+            assert!(score <= alpha, "score={} exact={} window=({}, {})", score, exact, alpha, beta);
+        }else if exact >= beta {
+            assert!(score >= beta, "score={} exact={} window=({}, {})", score, exact, alpha, beta);
+        }else{
+            assert_eq!(score, exact, "window=({}, {})", alpha, beta);
+        }
+    }
+
+    #[test]
+    fn solve_matches_minimax() {
+        let engine = Arc::new(Engine::new());
+        let shared = SearchShared::new(&engine, 1);
+        let mut rng = Rng::new(17);
+        let mut checked = 0;
+        while checked < 40 {
+            let empties = 1 + (rng.next_u64() % 9) as u32;
+            let board_info = match random_position(empties, &mut rng) {
+                Some(board_info) => board_info,
+                None => continue,
+            };
+            let exact = minimax(&board_info);
+            let mut pv = Vec::new();
+            assert_eq!(solve(-BOARDSIZE, BOARDSIZE, &board_info, EVAL_PERFECT, &shared, &mut pv), exact, "{}", board_info_to_string(&board_info));
+            assert_eq!(solve(-BOARDSIZE, BOARDSIZE, &board_info, EVAL_WIN, &shared, &mut pv), exact.signum(), "{}", board_info_to_string(&board_info));
+            for &(alpha, beta) in [(exact - 1, exact + 1), (exact, exact + 1), (exact - 1, exact), (exact + 2, exact + 10), (exact - 10, exact - 2)].iter() {
+                assert_within_window(solve(alpha, beta, &board_info, EVAL_PERFECT, &shared, &mut pv), exact, alpha, beta);
+            }
+            checked += 1;
+        }
+    }
+
+    #[test]
+    fn solve_follows_its_principal_variation() {
+        let engine = Arc::new(Engine::new());
+        let shared = SearchShared::new(&engine, 1);
+        let mut rng = Rng::new(71);
+        let mut checked = 0;
+        while checked < 20 {
+            let board_info = match random_position(8, &mut rng) {
+                Some(board_info) => board_info,
+                None => continue,
+            };
+            let mut pv = Vec::new();
+            let score = solve(-BOARDSIZE, BOARDSIZE, &board_info, EVAL_PERFECT, &shared, &mut pv);
+            // 読み筋の手はすべて合法手で，たどった局面の値は読み切りの値と一致する
+            let mut tmp_board_info = board_info.clone();
+            let mut sign = 1;
+            for &place_bit in pv.iter() {
+                assert!((place_bit == 0 && make_legal_board(&tmp_board_info) == 0) || place_bit & make_legal_board(&tmp_board_info) != 0);
+                place(place_bit, &mut tmp_board_info);
+                swap(&mut tmp_board_info);
+                sign = -sign;
+            }
+            assert_eq!(sign * minimax(&tmp_board_info), score, "{}", board_info_to_string(&board_info));
+            checked += 1;
+        }
+    }
+}
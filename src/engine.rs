@@ -0,0 +1,102 @@
+// 思考エンジン: 探索に用いるもの（実行時の設定，評価関数の重み，ProbCutのパラメータ，定石，スレッド数）をまとめて持つ
+// 探索はSearchSharedを通してEngineを参照するので，設定の違うエンジンを同じプロセスで同時に動かしたり，対局の合間に設定を変えたりできる
+
+use crate::board::*;
+use crate::book::Book;
+use crate::config::Config;
+use crate::eval::*;
+use crate::pattern::PatternWeights;
+use crate::pool::ThreadPool;
+use crate::probcut::MpcParams;
+
+pub struct Engine {
+    pub config: Config,              // 実行時の設定（eval_normalの重み，深さ，定石の手の選び方）
+    pub book: Book,                  // 対局に用いる定石
+    pattern_weights: PatternWeights, // EVAL_PATTERNの重み
+    pattern_fingerprint: u64,        // pattern_weightsの指紋（重みが大きいので設定したときに求めておく）
+    mpc_params: Vec<MpcParams>,      // Multi-ProbCutのパラメータ（評価関数ごとに1つまで）
+    midgame_evaluator: i8,           // 中盤に用いる評価関数（EVAL_NORMALかEVAL_PATTERN）
+    threads: usize,                  // 探索に使うスレッド数（0ならこのマシンで同時に動かせるスレッド数）
+}
+
+impl Engine {
+    // コンパイル時の定数と同じ設定，組み込みのProbCutのパラメータ，空の定石をもつエンジンを作る
+    pub fn new() -> Engine {
+        let pattern_weights = PatternWeights::new();
+        return Engine {
+            config: Config::new(),
+            book: Book::new(),
+            pattern_fingerprint: pattern_weights.fingerprint(),
+            pattern_weights,
+            mpc_params: vec![MpcParams::built_in()],
+            midgame_evaluator: EVAL_NORMAL,
+            threads: 0,
+        };
+    }
+
+    // パターンの重みを設定し，中盤の評価関数をEVAL_PATTERNにする
+    pub fn set_pattern_weights(&mut self, weights: PatternWeights) {
+        self.pattern_fingerprint = weights.fingerprint();
+        self.pattern_weights = weights;
+        self.midgame_evaluator = EVAL_PATTERN;
+    }
+
+    // EVAL_PATTERNの重み
+    pub fn pattern_weights(&self) -> &PatternWeights {
+        return &self.pattern_weights;
+    }
+
+    // 評価関数way_of_evalが今用いている重みの指紋
+    pub fn weights_fingerprint(&self, way_of_eval: i8) -> u64 {
+        return match way_of_eval {
+            EVAL_NORMAL  => normal_weights_fingerprint(&self.config.weights),
+            EVAL_PATTERN => self.pattern_fingerprint,
+            _            => 0,
+        };
+    }
+
+    // ProbCutのパラメータを加える（同じ評価関数について求めたものがあれば置き換える）
+    pub fn set_mpc_params(&mut self, params: MpcParams) {
+        self.mpc_params.retain(|p| p.evaluator != params.evaluator);
+        self.mpc_params.push(params);
+    }
+
+    // 評価関数way_of_evalとその今の重みについて求めたProbCutのパラメータ，なければNone（ProbCutをしない）
+    // 重みを学習し直したり設定で変えたりしたあとは，古い重みについて求めたパラメータは使われない
+    pub fn mpc_params(&self, way_of_eval: i8) -> Option<&MpcParams> {
+        let fingerprint = self.weights_fingerprint(way_of_eval);
+        return self.mpc_params.iter().find(|p| p.evaluator == way_of_eval && p.weights_fingerprint == fingerprint);
+    }
+
+    // 中盤に用いる評価関数を設定する（EVAL_NORMALかEVAL_PATTERN）
+    pub fn set_midgame_evaluator(&mut self, way_of_eval: i8) {
+        assert!(way_of_eval == EVAL_NORMAL || way_of_eval == EVAL_PATTERN, "not a midgame evaluator");
+        self.midgame_evaluator = way_of_eval;
+    }
+
+    // 中盤に用いる評価関数
+    pub fn midgame_evaluator(&self) -> i8 {
+        return self.midgame_evaluator;
+    }
+
+    // 探索に使うスレッド数を設定する（0ならこのマシンで同時に動かせるスレッド数）
+    pub fn set_thread_count(&mut self, n: usize) {
+        self.threads = n;
+    }
+
+    // 探索に使うスレッド数
+    pub fn thread_count(&self) -> usize {
+        return if self.threads == 0 {ThreadPool::default_size()} else {self.threads};
+    }
+
+    // 評価関数nでboard_infoを評価する
+    pub fn evaluate(&self, n: i8, board_info: &BoardInfo) -> i32 {
+        return evaluate(n, board_info, &self.config.weights, &self.pattern_weights);
+    }
+}
+
+impl Default for Engine {
+    fn default() -> Self {
+        return Engine::new();
+    }
+}
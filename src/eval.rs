@@ -0,0 +1,326 @@
+use crate::board::{BoardInfo, BOARDSIZE, make_legal_board, make_stable_boards, swap};
+use crate::pattern::{eval_pattern, PatternWeights};
+
+// evaluateするとき，どの関数で計算するか定める
+pub const EVAL_BY_POINTTABLE: i8 = -1; // デバッグ用
+pub const EVAL_NORMAL: i8 = 0;
+pub const EVAL_PERFECT: i8 = 1; // 完全読み切り（個数も読む）
+pub const EVAL_WIN: i8 = 2; // 勝つかどうかだけ読む（個数は読まない）
+pub const EVAL_PATTERN: i8 = 3; // パターンの重み（ファイルから読み込む）による中盤の評価
+
+// 各評価関数で読む深さの既定値（実行時の設定で変えられる）
+// 中盤の深さは反復深化の上限で，1CPU・持ち時間20秒では時間切れで12〜14手までしか届かない
+// 読み切りの深さは1CPUで測ったもの: 完全読みは18手で1秒以内（20手では2〜7秒，21手では6〜41秒），必勝読みは20手で5秒以内
+pub const EVAL_BY_POINTTABLE_DEPTH: i8 = 8;
+pub const EVAL_NORMAL_DEPTH: i8 = 20;
+pub const EVAL_PERFECT_DEPTH: i8 = 18;
+pub const EVAL_WIN_DEPTH: i8 = 20;
+pub const EVAL_PATTERN_DEPTH: i8 = 20;
+
+// eval_normalにおける重みの既定値（実行時の設定で変えられる）
+pub const WEIGHT_STABLE:   i32 = 100;
+pub const WEIGHT_WING:     i32 = -310;
+pub const WEIGHT_XMOVE:    i32 = -450;
+pub const WEIGHT_CMOVE:    i32 = -550;
+pub const WEIGHT_MOBILITY: i32 = 135;
+pub const WEIGHT_OPENNESS: i32 = -10;
+
+// eval_normalの特徴の数と，設定ファイルでの各重みの名前（normal_features()の順）
+pub const NORMAL_FEATURES: usize = 6;
+pub const NORMAL_WEIGHT_NAMES: [&str; NORMAL_FEATURES] = ["WEIGHT_STABLE", "WEIGHT_WING", "WEIGHT_XMOVE", "WEIGHT_CMOVE", "WEIGHT_MOBILITY", "WEIGHT_OPENNESS"];
+pub const DEFAULT_NORMAL_WEIGHTS: [i32; NORMAL_FEATURES] = [WEIGHT_STABLE, WEIGHT_WING, WEIGHT_XMOVE, WEIGHT_CMOVE, WEIGHT_MOBILITY, WEIGHT_OPENNESS];
+
+// 重みの指紋（各重みのビット列のFNV-1aハッシュ），ProbCutのパラメータがどの重みについて求めたものかを確かめるのに用いる
+pub fn weights_fingerprint<I: Iterator<Item = u32>>(words: I) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for word in words {
+        for byte in word.to_le_bytes().iter() {
+            hash ^= *byte as u64;
+            hash = hash.wrapping_mul(0x100000001b3);
+        }
+    }
+    return hash;
+}
+
+// eval_normalの重みの指紋
+pub fn normal_weights_fingerprint(weights: &[i32; NORMAL_FEATURES]) -> u64 {
+    return weights_fingerprint(weights.iter().map(|&weight| weight as u32));
+}
+
+// 重みを設定ファイル（Config::parse()）で読み込める形式のテキストにする
+pub fn normal_weights_to_text(weights: &[i32; NORMAL_FEATURES]) -> String {
+    let mut text = String::new();
+    for (name, weight) in NORMAL_WEIGHT_NAMES.iter().zip(weights.iter()) {
+        text += &format!("{} {}\n", name, weight);
+    }
+    return text;
+}
+
+// どの関数で評価するか定める（eval_normal，eval_patternにはそれぞれnormal_weights，pattern_weightsを用いる）
+pub fn evaluate(n: i8, board_info: &BoardInfo, normal_weights: &[i32; NORMAL_FEATURES], pattern_weights: &PatternWeights) -> i32 {
+    return match n {
+        EVAL_BY_POINTTABLE => eval_by_pointtable(board_info),
+        EVAL_NORMAL        => eval_normal(board_info, normal_weights),
+        EVAL_PERFECT       => eval_perfect(board_info),
+        EVAL_WIN           => eval_win(board_info),
+        EVAL_PATTERN       => eval_pattern(board_info, pattern_weights),
+        _                  => panic!("there is not such way of evaluation"),
+    };
+}
+
+// 得点テーブルを用いて盤面を評価する（デバッグ用）
+pub fn eval_by_pointtable(board_info: &BoardInfo) -> i32 {
+    let mut point: i32 = 0;
+    point += (board_info.player_board & 0x8100000000000081).count_ones() as i32 * (100); // A1
+    point += (board_info.player_board & 0x4281000000008142).count_ones() as i32 * (-50); // B1
+    point += (board_info.player_board & 0x2400810000810024).count_ones() as i32 * (10);  // C1
+    point += (board_info.player_board & 0x0042000000004200).count_ones() as i32 * (-70); // B2
+    point += (board_info.player_board & 0x0024420000422400).count_ones() as i32 * (-5);  // C2
+    point += (board_info.player_board & 0x0018244242241800).count_ones() as i32 * (-10); // D2,C3
+    point += (board_info.player_board & 0x0000182424180000).count_ones() as i32 * (-5);  // D3
+    
+    point -= (board_info.opponent_board & 0x8100000000000081).count_ones() as i32 * (100); // A1
+    point -= (board_info.opponent_board & 0x4281000000008142).count_ones() as i32 * (-50); // B1
+    point -= (board_info.opponent_board & 0x2400810000810024).count_ones() as i32 * (10);  // C1
+    point -= (board_info.opponent_board & 0x0042000000004200).count_ones() as i32 * (-70); // B2
+    point -= (board_info.opponent_board & 0x0024420000422400).count_ones() as i32 * (-5);  // C2
+    point -= (board_info.opponent_board & 0x0018244242241800).count_ones() as i32 * (-10); // D2,C3
+    point -= (board_info.opponent_board & 0x0000182424180000).count_ones() as i32 * (-5);  // D3
+
+    return point;
+}
+
+// 指定したますの開放度を計算する
+pub fn count_openness(empty_board: u64, bit: u64) -> i32 {
+    let mut count = 0;
+    if bit & 0x00000000000000ff == 0 { // 下にますがある
+        if (bit >> 8) & empty_board != 0 { // 下が空いている
+            count += 1;
+        }
+    }
+    if bit & 0x80808080808080ff == 0 { // 左下
+        if (bit >> 7) & empty_board != 0 { // 左下
+            count += 1;
+        }
+    }
+    if bit & 0x8080808080808080 == 0 { // 左
+        if (bit << 1) & empty_board != 0 { // 左
+            count += 1;
+        }
+    }
+    if bit & 0xff80808080808080 == 0 { // 左上
+        if (bit << 9) & empty_board != 0 { // 左上
+            count += 1;
+        }
+    }
+    if bit & 0xff00000000000000 == 0 { // 上
+        if (bit << 8) & empty_board != 0 { // 上
+            count += 1;
+        }
+    }
+    if bit & 0xff01010101010101 == 0 { // 右上
+        if (bit << 7) & empty_board != 0 { // 右上
+            count += 1;
+        }
+    }
+    if bit & 0x0101010101010101 == 0 { // 右
+        if (bit >> 1) & empty_board != 0 { // 右
+            count += 1;
+        }
+    }
+    if bit & 0x01010101010101ff == 0 { // 右下
+        if (bit >> 9) & empty_board != 0 { // 右下
+            count += 1;
+        }
+    }
+    return count;
+}
+
+// 中盤に用いる評価関数（weightsはNORMAL_WEIGHT_NAMESの順の重み）
+pub fn eval_normal(board_info: &BoardInfo, weights: &[i32; NORMAL_FEATURES]) -> i32 {
+    if board_info.player_board.count_ones() == 0 as u32 {
+        return -100000000;
+    }
+    if board_info.opponent_board.count_ones() == 0 as u32 {
+        return 100000000;
+    }
+
+    let features = normal_features(board_info);
+    let mut score = 0;
+    for i in 0..NORMAL_FEATURES {
+        score += features[i] * weights[i];
+    }
+    return score;
+}
+
+// eval_normalの特徴（確定石，ウイング，X打ち，C打ち，着手可能手数，開放度のそれぞれの自分と相手の差）
+pub fn normal_features(board_info: &BoardInfo) -> [i32; NORMAL_FEATURES] {
+    let empty_board = !(board_info.player_board | board_info.opponent_board);
+    
+    // ウイング，危険なC打ちをカウント
+    let mut player_wing_count = 0;
+    let mut player_c_place_count = 0;
+    // 上の辺
+    if empty_board & 0x8100000000000000 == 0x8100000000000000 { /* 隅にはなにも置かれていない */
+        if board_info.player_board & 0x3c00000000000000 == 0x3c00000000000000 { // ブロックができている
+            if (board_info.player_board & 0xff00000000000000 == 0x7c00000000000000 && empty_board & 0x0200000000000000 == 0x0200000000000000)
+                || (board_info.player_board & 0xff00000000000000 == 0x3e00000000000000 && empty_board & 0x4000000000000000 == 0x4000000000000000) /* ウイングの形ができている */ {
+                player_wing_count += 1;
+            }
+        }else{
+            player_c_place_count += (board_info.player_board & 0x4200000000000000).count_ones() as i32;
+        }
+    }
+    // 左の辺
+    if empty_board & 0x8000000000000080 == 0x8000000000000080 { /* 隅にはなにも置かれていない */
+        if board_info.player_board & 0x0000808080800000 == 0x0000808080800000 { // ブロックができている
+            if (board_info.player_board & 0x8080808080808080 == 0x0080808080800000 && empty_board & 0x0000000000008000 == 0x0000000000008000)
+                || (board_info.player_board & 0x8080808080808080 == 0x0000808080808000 && empty_board & 0x0080000000000000 == 0x0080000000000000) /* ウイングの形ができている */ {
+                player_wing_count += 1;
+            }
+        }else{
+            player_c_place_count += (board_info.player_board & 0x0080000000008000).count_ones() as i32;
+        }
+    }
+    // 右の辺
+    if empty_board & 0x0100000000000001 == 0x0100000000000001 { /* 隅にはなにも置かれていない */
+        if board_info.player_board & 0x0000010101010000 == 0x0000010101010000 { // ブロックができている
+            if (board_info.player_board & 0x0101010101010101 == 0x0001010101010000 && empty_board & 0x0000000000000100 == 0x0000000000000100)
+                || (board_info.player_board & 0x0101010101010101 == 0x0000010101010100 && empty_board & 0x0001000000000000 == 0x0001000000000000) /* ウイングの形ができている */ {
+                player_wing_count += 1;
+            }
+        }else{
+            player_c_place_count += (board_info.player_board & 0x0001000000000100).count_ones() as i32;
+        }
+    }
+    // 上の辺
+    if empty_board & 0x0000000000000081 == 0x0000000000000081 { /* 隅にはなにも置かれていない */
+        if board_info.player_board & 0x000000000000003c == 0x000000000000003c { // ブロックができている
+            if (board_info.player_board & 0x00000000000000ff == 0x000000000000007c && empty_board & 0x0000000000000002 == 0x0000000000000002)
+                || (board_info.player_board & 0x00000000000000ff == 0x000000000000003e && empty_board & 0x0000000000000040 == 0x0000000000000040) /* ウイングの形ができている */ {
+                player_wing_count += 1;
+            }
+        }else{
+            player_c_place_count += (board_info.player_board & 0x0000000000000042).count_ones() as i32;
+        }
+    }
+    let mut opponent_wing_count = 0;
+    let mut opponent_c_place_count = 0;
+    // 上の辺
+    if empty_board & 0x8100000000000000 == 0x8100000000000000 { /* 隅にはなにも置かれていない */
+        if board_info.opponent_board & 0x3c00000000000000 == 0x3c00000000000000 { // ブロックができている
+            if (board_info.opponent_board & 0xff00000000000000 == 0x7c00000000000000 && empty_board & 0x0200000000000000 == 0x0200000000000000)
+                || (board_info.opponent_board & 0xff00000000000000 == 0x3e00000000000000 && empty_board & 0x4000000000000000 == 0x4000000000000000) /* ウイングの形ができている */ {
+                opponent_wing_count += 1;
+            }
+        }else{
+            opponent_c_place_count += (board_info.opponent_board & 0x4200000000000000).count_ones() as i32;
+        }
+    }
+    // 左の辺
+    if empty_board & 0x8000000000000080 == 0x8000000000000080 { /* 隅にはなにも置かれていない */
+        if board_info.opponent_board & 0x0000808080800000 == 0x0000808080800000 { // ブロックができている
+            if (board_info.opponent_board & 0x8080808080808080 == 0x0080808080800000 && empty_board & 0x0000000000008000 == 0x0000000000008000)
+                || (board_info.opponent_board & 0x8080808080808080 == 0x0000808080808000 && empty_board & 0x0080000000000000 == 0x0080000000000000) /* ウイングの形ができている */ {
+                opponent_wing_count += 1;
+            }
+        }else{
+            opponent_c_place_count += (board_info.opponent_board & 0x0080000000008000).count_ones() as i32;
+        }
+    }
+    // 右の辺
+    if empty_board & 0x0100000000000001 == 0x0100000000000001 { /* 隅にはなにも置かれていない */
+        if board_info.opponent_board & 0x0000010101010000 == 0x0000010101010000 { // ブロックができている
+            if (board_info.opponent_board & 0x0101010101010101 == 0x0001010101010000 && empty_board & 0x0000000000000100 == 0x0000000000000100)
+                || (board_info.opponent_board & 0x0101010101010101 == 0x0000010101010100 && empty_board & 0x0001000000000000 == 0x0001000000000000) /* ウイングの形ができている */ {
+                opponent_wing_count += 1;
+            }
+        }else{
+            opponent_c_place_count += (board_info.opponent_board & 0x0001000000000100).count_ones() as i32;
+        }
+    }
+    // 上の辺
+    if empty_board & 0x0000000000000081 == 0x0000000000000081 { /* 隅にはなにも置かれていない */
+        if board_info.opponent_board & 0x000000000000003c == 0x000000000000003c { // ブロックができている
+            if (board_info.opponent_board & 0x00000000000000ff == 0x000000000000007c && empty_board & 0x0000000000000002 == 0x0000000000000002)
+                || (board_info.opponent_board & 0x00000000000000ff == 0x000000000000003e && empty_board & 0x0000000000000040 == 0x0000000000000040) /* ウイングの形ができている */ {
+                opponent_wing_count += 1;
+            }
+        }else{
+            opponent_c_place_count += (board_info.opponent_board & 0x0000000000000042).count_ones() as i32;
+        }
+    }
+    
+    // 確定石の数
+    let (player_stable_board, opponent_stable_board) = make_stable_boards(board_info);
+    let player_stable_count = player_stable_board.count_ones() as i32;
+    let opponent_stable_count = opponent_stable_board.count_ones() as i32;
+
+    // 危険なX打ちの数
+    let mut player_x_place_count = 0;
+    if board_info.player_board & 0x0040000000000000 == 0x0040000000000000 && empty_board & 0x8000000000000000 == 0x8000000000000000 { // 左上
+        player_x_place_count += 1;
+    }
+    if board_info.player_board & 0x0002000000000000 == 0x0002000000000000 && empty_board & 0x0100000000000000 == 0x0100000000000000 { // 右上
+        player_x_place_count += 1;
+    }
+    if board_info.player_board & 0x0000000000000200 == 0x0000000000000200 && empty_board & 0x0000000000000001 == 0x0000000000000001 { // 右下
+        player_x_place_count += 1;
+    }
+    if board_info.player_board & 0x0000000000004000 == 0x0000000000004000 && empty_board & 0x0000000000000080 == 0x0000000000000080 { // 左下
+        player_x_place_count += 1;
+    }
+
+    let mut opponent_x_place_count = 0;
+    if board_info.opponent_board & 0x0040000000000000 == 0x0040000000000000 && empty_board & 0x8000000000000000 == 0x8000000000000000 { // 左上
+        opponent_x_place_count += 1;
+    }
+    if board_info.opponent_board & 0x0002000000000000 == 0x0002000000000000 && empty_board & 0x0100000000000000 == 0x0100000000000000 { // 右上
+        opponent_x_place_count += 1;
+    }
+    if board_info.opponent_board & 0x0000000000000200 == 0x0000000000000200 && empty_board & 0x0000000000000001 == 0x0000000000000001 { // 右下
+        opponent_x_place_count += 1;
+    }
+    if board_info.opponent_board & 0x0000000000004000 == 0x0000000000004000 && empty_board & 0x0000000000000080 == 0x0000000000000080 { // 左下
+        opponent_x_place_count += 1;
+    }
+
+    // 着手可能手数
+    let player_legal_board_count = make_legal_board(&board_info).count_ones() as i32;
+    let mut tmp_board_info = board_info.clone();
+    swap(&mut tmp_board_info);
+    let opponent_legal_board_count = make_legal_board(&tmp_board_info).count_ones() as i32;
+
+    // 開放度計算
+    let mut player_openness = 0;
+    let mut opponent_openness = 0;
+    let mut mask: u64 = 0x8000000000000000;
+    for _ in 0..BOARDSIZE {
+        if board_info.player_board & mask != 0 {
+            player_openness += count_openness(empty_board, mask);
+        }else if board_info.opponent_board & mask != 0 {
+            opponent_openness += count_openness(empty_board, mask);
+        }
+        mask = mask >> 1;
+    }
+
+    return [
+        player_stable_count - opponent_stable_count,
+        player_wing_count - opponent_wing_count,
+        player_x_place_count - opponent_x_place_count,
+        player_c_place_count - opponent_c_place_count,
+        player_legal_board_count - opponent_legal_board_count,
+        player_openness - opponent_openness,
+    ];
+}
+
+// 完全読み切り（個数も読む）
+pub fn eval_perfect(board_info: &BoardInfo) -> i32 {
+    return board_info.player_board.count_ones() as i32 - board_info.opponent_board.count_ones() as i32;
+}
+
+// 勝つかどうかだけ読む（個数は読まない）
+pub fn eval_win(board_info: &BoardInfo) -> i32 {
+    let diff = board_info.player_board.count_ones() as i32 - board_info.opponent_board.count_ones() as i32;
+    return if diff > 0 {1} else if diff == 0 {0} else {-1};
+}
@@ -0,0 +1,366 @@
+// GGF（Generic Game Format）の棋譜の読み書き
+// 1局は"(;GM[Othello]PB[黒の名前]PW[白の名前]TI[持ち時間]RE[結果]BO[8 盤面 手番]B[d3/評価値/時間]W[c5]...;)"の形で，1つのファイルに何局でも並べられる
+// 盤面は*が黒，Oが白，-が空き（board_info_to_string()と同じ並び）で，手はパスならPA，評価値は打った側からみた石差，時間は秒
+
+use std::fs;
+
+use crate::board::*;
+
+// GGFの1手
+#[derive(Clone)]
+pub struct GgfMove {
+    pub color: i8,         // 打った側の色
+    pub place_bit: u64,    // 打った手（0ならパス）
+    pub eval: Option<f32>, // 打った側からみた評価値（石差）
+    pub time: Option<f32>, // 思考時間（秒）
+}
+
+// GGFの1局
+#[derive(Clone)]
+pub struct GgfGame {
+    pub black_player: String,             // PB，書かれていなければ空
+    pub white_player: String,             // PW
+    pub time_control: String,             // TI（"15:00//02:00"のような持ち時間の書式のまま）
+    pub result: String,                   // RE（"+12.000"のような黒からみた石差，":r"などの終局理由がつくこともある）
+    pub properties: Vec<(String, String)>, // それ以外のプロパティ（PC，DTなど）を書かれていた順に
+    pub start: BoardInfo,                 // BO，開始局面
+    pub moves: Vec<GgfMove>,              // 打った手（パスも含む）
+}
+
+// BOの値（"8 --------(8行) *"）を局面にする
+fn parse_board(value: &str) -> Result<BoardInfo, String> {
+    let rest = value.trim_start().strip_prefix('8').ok_or(format!("unsupported board size: {}", value))?;
+    return string_to_board_info(rest);
+}
+
+// 局面をBOの値にする（*が黒）
+fn board_to_string(board_info: &BoardInfo) -> String {
+    let chars: Vec<char> = board_info_to_string(board_info).replace('X', "*").chars().collect();
+    let mut s = String::from("8");
+    for row in chars[..BOARDSIZE as usize].chunks(LINESIZE as usize) {
+        s.push(' ');
+        s.extend(row.iter());
+    }
+    s.push(' ');
+    s.push(chars[BOARDSIZE as usize + 1]);
+    return s;
+}
+
+// "1:02.5"のような分:秒の書き方も許して秒にする
+fn parse_time(value: &str) -> Option<f32> {
+    let mut seconds: f32 = 0.0;
+    for part in value.split(':') {
+        seconds = seconds * 60.0 + part.parse::<f32>().ok()?;
+    }
+    return Some(seconds);
+}
+
+// B，Wの値（"d3/1.23/0.01"，評価値と時間は省略できる）を1手にする
+fn parse_move(color: i8, value: &str) -> Result<GgfMove, String> {
+    let fields: Vec<&str> = value.split('/').map(|field| field.trim()).collect();
+    let place_bit = if fields[0].eq_ignore_ascii_case("PA") {0} else {string_to_move(fields[0])?};
+    let eval = match fields.get(1) {
+        Some(field) if !field.is_empty() => Some(field.parse().map_err(|_| format!("invalid evaluation: {}", value))?),
+        _ => None,
+    };
+    let time = match fields.get(2) {
+        Some(field) if !field.is_empty() => Some(parse_time(field).ok_or(format!("invalid time: {}", value))?),
+        _ => None,
+    };
+    return Ok(GgfMove {
+        color,
+        place_bit,
+        eval,
+        time,
+    });
+}
+
+impl GgfGame {
+    // startの局面から始まる，手もプロパティもない棋譜
+    pub fn new(start: &BoardInfo) -> GgfGame {
+        return GgfGame {
+            black_player: String::new(),
+            white_player: String::new(),
+            time_control: String::new(),
+            result: String::new(),
+            properties: Vec::new(),
+            start: start.clone(),
+            moves: Vec::new(),
+        };
+    }
+
+    // "(;"から";)"までの1局を読み込む
+    // 手はplace()で打ち直して合法かを確かめ，書かれていないパスは補う
+    pub fn parse(text: &str) -> Result<GgfGame, String> {
+        let body = text.trim();
+        if !body.starts_with("(;") || !body.ends_with(";)") {
+            return Err("a game must be enclosed in (; and ;)".to_string());
+        }
+        let body: Vec<char> = body[2..body.len()-2].chars().collect();
+
+        let mut game = GgfGame::new(&BoardInfo::new());
+        let mut moves = Vec::<GgfMove>::new();
+        let mut i = 0;
+        while i < body.len() {
+            if body[i].is_whitespace() {
+                i += 1;
+                continue;
+            }
+            let key_start = i;
+            while i < body.len() && body[i].is_ascii_uppercase() {
+                i += 1;
+            }
+            let key: String = body[key_start..i].iter().collect();
+            if key.is_empty() || i >= body.len() || body[i] != '[' {
+                return Err(format!("expected a property at: {}", body[key_start..].iter().take(16).collect::<String>()));
+            }
+            let value_start = i + 1;
+            while i < body.len() && body[i] != ']' {
+                i += 1;
+            }
+            if i >= body.len() {
+                return Err(format!("unterminated property: {}", key));
+            }
+            let value: String = body[value_start..i].iter().collect();
+            i += 1;
+
+            match key.as_str() {
+                "GM" => {
+                    if !value.eq_ignore_ascii_case("Othello") {
+                        return Err(format!("unsupported game: {}", value));
+                    }
+                },
+                "PB" => game.black_player = value,
+                "PW" => game.white_player = value,
+                "TI" => game.time_control = value,
+                "RE" => game.result = value,
+                "BO" => game.start = parse_board(&value)?,
+                "B" => moves.push(parse_move(BLACK, &value)?),
+                "W" => moves.push(parse_move(WHITE, &value)?),
+                _ => game.properties.push((key, value)),
+            };
+        }
+
+        let mut board_info = game.start.clone();
+        for (n, mov) in moves.into_iter().enumerate() {
+            if is_game_over(&board_info) {
+                return Err(format!("move {}: the game is already over", n + 1));
+            }
+            if mov.color != board_info.now_turn && make_legal_board(&board_info) == 0 { // パスを補う
+                place(0, &mut board_info);
+                swap(&mut board_info);
+                game.moves.push(GgfMove {
+                    color: -mov.color,
+                    place_bit: 0,
+                    eval: None,
+                    time: None,
+                });
+            }
+            if mov.color != board_info.now_turn {
+                return Err(format!("move {}: not {}'s turn", n + 1, if mov.color == BLACK {"black"} else {"white"}));
+            }
+            if place(mov.place_bit, &mut board_info) == PLACE_ERR {
+                return Err(format!("move {}: illegal move: {}", n + 1, move_to_string(mov.place_bit)));
+            }
+            swap(&mut board_info);
+            game.moves.push(mov);
+        }
+        return Ok(game);
+    }
+
+    // parse()で読み込める形式の文字列にする
+    pub fn to_text(&self) -> String {
+        let mut text = String::from("(;GM[Othello]");
+        for (key, value) in self.properties.iter() {
+            text += &format!("{}[{}]", key, value);
+        }
+        for (key, value) in [("PB", &self.black_player), ("PW", &self.white_player), ("TI", &self.time_control), ("RE", &self.result)].iter() {
+            if !value.is_empty() {
+                text += &format!("{}[{}]", key, value);
+            }
+        }
+        text += &format!("BO[{}]", board_to_string(&self.start));
+        for mov in self.moves.iter() {
+            let place = if mov.place_bit == 0 {"PA".to_string()} else {move_to_string(mov.place_bit).to_lowercase()};
+            let eval = mov.eval.map_or(String::new(), |eval| format!("{:.2}", eval));
+            let time = mov.time.map_or(String::new(), |time| format!("{:.2}", time));
+            let value = if !time.is_empty() {
+                format!("{}/{}/{}", place, eval, time)
+            }else if !eval.is_empty() {
+                format!("{}/{}", place, eval)
+            }else{
+                place
+            };
+            text += &format!("{}[{}]", if mov.color == BLACK {"B"} else {"W"}, value);
+        }
+        text += ";)";
+        return text;
+    }
+
+    // nameのプロパティの値（PB，PW，TI，REはそれぞれのフィールド）
+    pub fn property(&self, name: &str) -> Option<&str> {
+        return self.properties.iter().find(|(key, _)| key == name).map(|(_, value)| value.as_str());
+    }
+
+    // 各局面，positions()[i]はmoves[i]を打つ前の局面で，最後の要素は最後の手を打ったあとの局面
+    pub fn positions(&self) -> Vec<BoardInfo> {
+        let mut board_info = self.start.clone();
+        let mut positions = vec![board_info.clone()];
+        for mov in self.moves.iter() {
+            place(mov.place_bit, &mut board_info);
+            swap(&mut board_info);
+            positions.push(board_info.clone());
+        }
+        return positions;
+    }
+
+    // 手順（パスは0），初期局面から始まる棋譜ならTranscript::from_moves()や定石に渡せる
+    pub fn line(&self) -> Vec<u64> {
+        return self.moves.iter().map(|mov| mov.place_bit).collect();
+    }
+
+    // 初期局面から始まる棋譜か
+    pub fn starts_from_initial_position(&self) -> bool {
+        let initial = BoardInfo::new();
+        return self.start.now_turn == initial.now_turn && self.start.player_board == initial.player_board && self.start.opponent_board == initial.opponent_board;
+    }
+}
+
+// "(;"から";)"までの棋譜をいくつも並べたテキストを読み込む
+pub fn parse_ggf(text: &str) -> Result<Vec<GgfGame>, String> {
+    let mut games = Vec::<GgfGame>::new();
+    let mut rest = text;
+    while let Some(start) = rest.find("(;") {
+        let end = rest[start..].find(";)").ok_or(format!("game {}: missing ;)", games.len() + 1))? + start + 2;
+        games.push(GgfGame::parse(&rest[start..end]).map_err(|e| format!("game {}: {}", games.len() + 1, e))?);
+        rest = &rest[end..];
+    }
+    return Ok(games);
+}
+
+// GGFのファイルを読み込む
+pub fn load_ggf(path: &str) -> Result<Vec<GgfGame>, String> {
+    let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+    return parse_ggf(&text).map_err(|e| format!("{}: {}", path, e));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::random::{random_game, Rng};
+
+    const INITIAL_BOARD: &str = "8 -------- -------- -------- ---O*--- ---*O--- -------- -------- -------- *";
+
+    fn same_position(a: &BoardInfo, b: &BoardInfo) -> bool {
+        return a.now_turn == b.now_turn && a.player_board == b.player_board && a.opponent_board == b.opponent_board;
+    }
+
+    // 終局までランダムに打った棋譜（評価値と時間は2桁の小数で表せる値にする）
+    fn random_ggf_game(rng: &mut Rng) -> GgfGame {
+        let mut game = GgfGame::new(&BoardInfo::new());
+        game.black_player = "alice".to_string();
+        game.white_player = "bob".to_string();
+        game.time_control = "05:00//02:00".to_string();
+        game.properties.push(("PC".to_string(), "test".to_string()));
+        let (positions, moves) = random_game(rng);
+        for (board_info, &place_bit) in positions.iter().zip(moves.iter()) {
+            let eval = (rng.next_u64() % 129) as f32 - 64.0;
+            game.moves.push(GgfMove {
+                color: board_info.now_turn,
+                place_bit,
+                eval: if place_bit == 0 {None} else {Some(eval)},
+                time: if rng.next_u64() & 1 == 0 {None} else {Some((rng.next_u64() % 1000) as f32 / 4.0)},
+            });
+        }
+        let (black_count, white_count, _) = get_result(positions.last().unwrap());
+        game.result = format!("{:+}", black_count as i32 - white_count as i32);
+        return game;
+    }
+
+    #[test]
+    fn text_round_trip_keeps_everything() {
+        let mut rng = Rng::new(22);
+        for _ in 0..100 {
+            let game = random_ggf_game(&mut rng);
+            let text = game.to_text();
+            let parsed = GgfGame::parse(&text).unwrap();
+            assert_eq!(parsed.black_player, game.black_player);
+            assert_eq!(parsed.white_player, game.white_player);
+            assert_eq!(parsed.time_control, game.time_control);
+            assert_eq!(parsed.result, game.result);
+            assert_eq!(parsed.property("PC"), Some("test"));
+            assert!(same_position(&parsed.start, &game.start));
+            assert!(parsed.starts_from_initial_position());
+            assert_eq!(parsed.moves.len(), game.moves.len());
+            for (a, b) in parsed.moves.iter().zip(game.moves.iter()) {
+                assert_eq!((a.color, a.place_bit, a.eval, a.time), (b.color, b.place_bit, b.eval, b.time));
+            }
+            assert!(is_game_over(parsed.positions().last().unwrap()));
+            assert_eq!(parsed.to_text(), text);
+        }
+    }
+
+    #[test]
+    fn parse_reads_a_hand_written_game() {
+        let text = format!("(;GM[Othello]PC[NIOS]DT[2003.12.15_13:24:03.MET]PB[foo]PW[bar]RE[+2.000:r]TI[15:00//02:00]TY[8]BO[{}]B[f5//1:02.5]W[D6/-1.50]B[c3/2/0.01];)", INITIAL_BOARD);
+        let game = GgfGame::parse(&text).unwrap();
+        assert_eq!(game.black_player, "foo");
+        assert_eq!(game.white_player, "bar");
+        assert_eq!(game.result, "+2.000:r");
+        assert_eq!(game.time_control, "15:00//02:00");
+        assert_eq!(game.property("DT"), Some("2003.12.15_13:24:03.MET"));
+        assert_eq!(game.property("PB"), None); // PBはフィールドに入る
+        assert!(game.starts_from_initial_position());
+        assert_eq!(line_to_string(&game.line()), "F5 D6 C3");
+        assert_eq!((game.moves[0].eval, game.moves[0].time), (None, Some(62.5)));
+        assert_eq!((game.moves[1].color, game.moves[1].eval, game.moves[1].time), (WHITE, Some(-1.5), None));
+        assert_eq!((game.moves[2].eval, game.moves[2].time), (Some(2.0), Some(0.01)));
+    }
+
+    #[test]
+    fn parse_inserts_missing_passes() {
+        // 黒が打つと終局する局面（白は打てない）
+        let text = "(;GM[Othello]BO[8 -------- -------- -------- ---OOO*- -------- -------- -------- -------- *]B[c4];)";
+        let game = GgfGame::parse(text).unwrap();
+        assert_eq!(line_to_string(&game.line()), "C4");
+        assert!(is_game_over(game.positions().last().unwrap()));
+
+        // PAを書かずに同じ色が続けて打った棋譜でも，パスを補って読み込める
+        let mut rng = Rng::new(2200);
+        let mut checked = 0;
+        while checked < 5 {
+            let game = random_ggf_game(&mut rng);
+            if game.moves.iter().all(|mov| mov.place_bit != 0) {
+                continue;
+            }
+            let mut without_passes = game.clone();
+            without_passes.moves.retain(|mov| mov.place_bit != 0);
+            let parsed = GgfGame::parse(&without_passes.to_text()).unwrap();
+            assert_eq!(parsed.line(), game.line());
+            checked += 1;
+        }
+    }
+
+    #[test]
+    fn parse_rejects_bad_games() {
+        assert!(GgfGame::parse("GM[Othello]").is_err());
+        assert!(GgfGame::parse("(;GM[Chess];)").is_err());
+        assert!(GgfGame::parse("(;GM[Othello]B[a1];)").is_err()); // 打てない手
+        assert!(GgfGame::parse("(;GM[Othello]W[f5];)").is_err()); // 白の番ではない
+        assert!(GgfGame::parse("(;GM[Othello]B[f5;)").is_err());
+        assert!(GgfGame::parse("(;GM[Othello]BO[10 * ];)").is_err());
+    }
+
+    #[test]
+    fn parse_ggf_reads_several_games() {
+        let mut rng = Rng::new(2222);
+        let games: Vec<GgfGame> = (0..3).map(|_| random_ggf_game(&mut rng)).collect();
+        let text: String = games.iter().map(|game| game.to_text() + "\n").collect();
+        let parsed = parse_ggf(&text).unwrap();
+        assert_eq!(parsed.len(), 3);
+        for (a, b) in parsed.iter().zip(games.iter()) {
+            assert_eq!(a.line(), b.line());
+        }
+        assert!(parse_ggf("(;GM[Othello]B[f5]").is_err());
+    }
+}
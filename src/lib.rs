@@ -0,0 +1,37 @@
+// オセロAIの思考ルーチン部
+// 盤面（board），定石（book），実行時の設定（config），思考エンジン（engine），評価関数（eval），探索（search），乱数（random），終盤の読み切り（endgame），置換表（tt），手の並べ替え（ordering），パターン評価（pattern），選択的探索（probcut），スレッドプール（pool），自己対戦（selfplay），盤面の対称変換（symmetry），学習（train），棋譜（transcript），GGFの棋譜（ggf），WTHORのデータベース（wthor）をライブラリとして公開する
+
+// 明示的なreturnや`0 as u64`のような書き方をそのまま使っているため，以下のlintは許容する
+#![allow(
+    clippy::needless_return,
+    clippy::unnecessary_cast,
+    clippy::precedence,
+    clippy::assign_op_pattern,
+    clippy::needless_borrow,
+    clippy::clone_on_copy,
+    clippy::unused_unit,
+    clippy::println_empty_string,
+    clippy::legacy_numeric_constants,
+    clippy::manual_swap,
+    clippy::useless_vec,
+)]
+
+pub mod board;
+pub mod book;
+pub mod config;
+pub mod endgame;
+pub mod engine;
+pub mod eval;
+pub mod ggf;
+pub mod ordering;
+pub mod pattern;
+pub mod pool;
+pub mod probcut;
+pub mod random;
+pub mod search;
+pub mod selfplay;
+pub mod symmetry;
+pub mod train;
+pub mod transcript;
+pub mod tt;
+pub mod wthor;
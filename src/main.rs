@@ -1,1110 +1,652 @@
+// 思考ルーチンをTCPクライアントとして動かすバイナリ
+
+// ライブラリ側と同じく，既存コードの書き方に合わせて以下のlintは許容する
+#![allow(
+    clippy::needless_return,
+    clippy::unnecessary_cast,
+    clippy::needless_borrow,
+    clippy::unused_unit,
+    clippy::println_empty_string,
+    clippy::assign_op_pattern,
+    clippy::char_lit_as_u8,
+    clippy::ptr_arg,
+    clippy::needless_range_loop,
+    clippy::needless_late_init,
+    clippy::bool_comparison,
+)]
+
 use std::str;
-use std::time::{Instant};
 use std::io::{BufRead, BufReader, Write, BufWriter};
 use std::net::{TcpStream, ToSocketAddrs};
 use std::env;
-use std::thread;
-use std::sync::mpsc;
-
-const BLACK: i8 = 1;
-const WHITE: i8 = -1;
-const NONE : i8 = 0;
-const TURN_CHANGE_FACTOR: i8 = -1;
-const BOARDSIZE: i32 = 64;
-const LINESIZE: i32 = 8;
-const MAX_TURNS: i8 = 60;
-
-// placeの返り値で用いる
-const CONTINUE: i8 = 0;
-const PLACE_ERR: i8 = -1;
-const GAME_SET: i8 = 1;
-
-// evaluateするとき，どの関数で計算するか定める
-const EVAL_BY_POINTTABLE: i8 = -1; // デバッグ用
-const EVAL_NORMAL: i8 = 0;
-const EVAL_PERFECT: i8 = 1; // 完全読み切り（個数も読む）
-const EVAL_WIN: i8 = 2; // 勝つかどうかだけ読む（個数は読まない）
-
-const EVAL_BY_POINTTABLE_DEPTH: i8 = 8;
-const EVAL_NORMAL_DEPTH: i8 = 8;
-const EVAL_PERFECT_DEPTH: i8 = 16;
-const EVAL_WIN_DEPTH: i8 = 18;
-
-// eval_normalにおける重み
-const WEIGHT_STABLE:   i32 = 100;
-const WEIGHT_WING:     i32 = -310;
-const WEIGHT_XMOVE:    i32 = -450;
-const WEIGHT_CMOVE:    i32 = -550;
-const WEIGHT_MOBILITY: i32 = 135;
-const WEIGHT_OPENNESS: i32 = -10;
-
-struct BoardInfo {
-    now_turn: i8,
-    now_index: i8,
-    player_board: u64,
-    opponent_board: u64,
-}
-
-impl Clone for BoardInfo {
-    fn clone(&self) -> Self {
-        return BoardInfo {
-            now_turn: self.now_turn.clone(),
-            now_index: self.now_index.clone(),
-            player_board: self.player_board.clone(),
-            opponent_board: self.opponent_board.clone(),
-        };
-    }
-}
-
-fn max(a: i32, b: i32) -> i32 {
-    return if a < b {b} else {a};
-}
-
-// 盤面の情報を簡易的に出力する
-fn print_board_info_simply(board_info: &BoardInfo) -> () {
-    let (black_count, white_count, _superior) = get_result(&board_info);
-    println!("{}'s TURN, BLACK:{}, WHITE:{}, INDEX:{}", 
-        match board_info.now_turn {
-            BLACK => "BLACK",
-            WHITE => "WHITE",
-            _     => panic!("there is not sych color"),
-        },
-        black_count,
-        white_count,
-        board_info.now_index,
-    );
-}
-
-// 盤面を出力する
-fn print_board_info(board_info: &BoardInfo, eval: i32) -> () {
-    println!("");
-    println!("********************");
-    let mut player_color: char = 'b';
-    let mut opponent_color: char = 'w';
-    let (black_count, white_count, _superior) = get_result(&board_info);
-    println!("{}'s TURN, BLACK:{}, WHITE:{}, INDEX:{}, PLAYER's EVAL:{}", 
-        match board_info.now_turn {
-            BLACK => "BLACK",
-            WHITE => {
-                player_color = 'w';
-                opponent_color = 'b';
-                "WHITE"
-            },
-            _     => panic!("there is not sych color"),
-        },
-        black_count,
-        white_count,
-        board_info.now_index,
-        eval
-    );
-    println!("");
-    println!("   A B C D E F G H ");
-    for i in (0..8).rev() {
-        print!(" {} ", i32::abs(8-i));
-        for j in (0..8).rev() {
-            if ((board_info.player_board >> (i*8)+j) & (1 as u64)) == 1 {
-                print!("{} ", player_color);
-            }else if ((board_info.opponent_board >> (i*8)+j) & (1 as u64)) == 1 {
-                print!("{} ", opponent_color);
-            }else{
-                print!("- ");
-            }
-        }
-        println!("");
-    }
-    println!("");
-    println!("********************");
-}
-
-// char2つによる文字の入力に対応する場所のビットを立てた盤面を返す
-fn point_to_bit(inp1: char, inp2: char) -> u64 {
-    let mut ret: u64 = 0x8000000000000000;
-    match inp1 {
-        'A' => (),
-        'B' => ret = ret >> 1,
-        'C' => ret = ret >> 2,
-        'D' => ret = ret >> 3,
-        'E' => ret = ret >> 4,
-        'F' => ret = ret >> 5,
-        'G' => ret = ret >> 6,
-        'H' => ret = ret >> 7,
-         _  => (),
-    }
-    let tmp: i32 = (inp2 as i32) - 48;
-    ret = ret >> ((tmp-1)*8);
-
-    return ret;
-}
-
-// ビットが1つだけ立っているu64の場所をchar2つに変換する
-fn bit_to_point(num: u64) -> (char, char) {
-    let mut count = 0;
-    let mut num_cpy = num;
-    while num_cpy != 0x8000000000000000 {
-        count = count + 1;
-        num_cpy = num_cpy << 1;
-    }
-    let c1: char = match count % LINESIZE {
-        0 => 'A',
-        1 => 'B',
-        2 => 'C',
-        3 => 'D',
-        4 => 'E',
-        5 => 'F',
-        6 => 'G',
-        7 => 'H',
-        _ => panic!("invalid rem"),
-    };
-    let c2: char = std::char::from_digit(((count / LINESIZE) + 1) as u32, 10).unwrap();
-    return (c1, c2);
-}
-
-// player_boardからみておけるマスにフラグが立っている盤面を返す
-fn make_legal_board(board_info: &BoardInfo) -> u64 {
-    let horizontal_side: u64 = board_info.opponent_board & 0x7e7e7e7e7e7e7e7e;
-    let vertical_side: u64 = board_info.opponent_board & 0x00FFFFFFFFFFFF00;
-    let all_side: u64 = board_info.opponent_board & 0x007e7e7e7e7e7e00;
-    let blank_board: u64 = !(board_info.player_board | board_info.opponent_board);
-    let mut tmp: u64;
-    let mut legal_board: u64;
-
-    // 左側から挟めるビットを立てる
-    tmp = horizontal_side & (board_info.player_board << 1);
-    for _ in 0..5 {
-        tmp |= horizontal_side & (tmp << 1)
-    }
-    legal_board = blank_board & (tmp << 1);
-
-    // 右
-    tmp = horizontal_side & (board_info.player_board >> 1);
-    for _ in 0..5 {
-        tmp |= horizontal_side & (tmp >> 1)
-    }
-    legal_board |= blank_board & (tmp >> 1);
-
-    // 上
-    tmp = vertical_side & (board_info.player_board << 8);
-    for _ in 0..5 {
-        tmp |= vertical_side & (tmp << 8)
-    }
-    legal_board |= blank_board & (tmp << 8);
-
-    // 下
-    tmp = vertical_side & (board_info.player_board >> 8);
-    for _ in 0..5 {
-        tmp |= vertical_side & (tmp >> 8)
-    }
-    legal_board |= blank_board & (tmp >> 8);
-
-    // 右上
-    tmp = all_side & (board_info.player_board << 7);
-    for _ in 0..5 {
-        tmp |= all_side & (tmp << 7)
-    }
-    legal_board |= blank_board & (tmp << 7);
-
-    // 左上
-    tmp = all_side & (board_info.player_board << 9);
-    for _ in 0..5 {
-        tmp |= all_side & (tmp << 9)
-    }
-    legal_board |= blank_board & (tmp << 9);
-
-    // 右下
-    tmp = all_side & (board_info.player_board >> 9);
-    for _ in 0..5 {
-        tmp |= all_side & (tmp >> 9)
-    }
-    legal_board |= blank_board & (tmp >> 9);
-
-    // 左下
-    tmp = all_side & (board_info.player_board >> 7);
-    for _ in 0..5 {
-        tmp |= all_side & (tmp >> 7)
-    }
-    legal_board |= blank_board & (tmp >> 7);
-
-    return legal_board;
-}
-
-// ビットを指定した方向に一つ動かす，ただし端は除かれる
-fn transfer(place_bit: &u64, direc: &i8) -> u64 {
-    return match direc {
-        0 => (place_bit << 8) & 0xffffffffffffff00, // 上
-        1 => (place_bit << 7) & 0x7f7f7f7f7f7f7f00, // 右上
-        2 => (place_bit >> 1) & 0x7f7f7f7f7f7f7f7f, // 右
-        3 => (place_bit >> 9) & 0x007f7f7f7f7f7f7f, // 右下
-        4 => (place_bit >> 8) & 0x00ffffffffffffff, // 下
-        5 => (place_bit >> 7) & 0x00fefefefefefefe, // 左下
-        6 => (place_bit << 1) & 0xfefefefefefefefe, // 左
-        7 => (place_bit << 9) & 0xfefefefefefefe00, // 左上
-        _ => panic!("transfer error."),
-    };
-}
-
-// playerが石をうつ部分の処理
-fn place(place_bit: u64, board_info: &mut BoardInfo) -> i8 {
-    
-    let legal_board: u64 = make_legal_board(&board_info);
-
-    // パス
-    if place_bit == 0 as u64 {
-        if legal_board == 0 as u64 { // 本当に着手可能な手がないならパス
-            // 相手側もパスなら終局
-            let tmp_board_info: BoardInfo = BoardInfo {
-                now_turn: board_info.now_turn.clone() * TURN_CHANGE_FACTOR,
-                now_index: board_info.now_index.clone(),
-                player_board: board_info.opponent_board.clone(),
-                opponent_board: board_info.player_board.clone(),
-            };
-            if make_legal_board(&tmp_board_info) == 0 { // 相手側もパスの場合終局
-                return GAME_SET;
-            }
-
-            return CONTINUE;
-        }else{ // おける場所があるのにパスすることはできない
-            return PLACE_ERR;
-        }
-    }
-
-    // 石をうつ
-    if (place_bit & legal_board) == place_bit { // 着手可能なら処理を続ける
-        // 石を裏返す処理
-        let mut rev: u64 = 0; // 裏返す部分のビットを立てる変数
-        for k in 0..8 { // kで方向を定める
-            let mut rev_sub: u64 = 0;
-            let mut tmp: u64 = transfer(&place_bit, &k);
-            // 相手のコマが続く限りrevの候補となるrev_subを更新し続ける
-            while (tmp != 0) && ((tmp & board_info.opponent_board) != 0) {
-                rev_sub |= tmp;
-                tmp = transfer(&tmp, &k);
-            }
-            if (tmp & board_info.player_board) != 0 { // 自分の色で挟まれていれば実際にrev_subをrevに加える
-                rev |= rev_sub;
-            }
-        }
-        board_info.player_board ^= place_bit | rev; // XORをとる
-        board_info.opponent_board ^= rev;
-        // 石を裏返す処理終了
-
-        board_info.now_index += 1;
-        // 60手目を打ち終わったら終局
-        if board_info.now_index > 60 {
-            return GAME_SET;
-        }
-    }else{ // 着手可能でない
-        return PLACE_ERR;
-    };
-
-    return CONTINUE;
-
-}
-
-// 終局かどうかを判定
-fn is_game_over(board_info: &BoardInfo) -> bool {
-    let player_legal_board = make_legal_board(board_info);
-    let tmp_board_info = board_info.clone();
-    let opponent_legal_board = make_legal_board(&tmp_board_info);
-    return player_legal_board == 0 as u64 && opponent_legal_board == 0 as u64;
-}
-
-// 手番入れ替え
-fn swap(board_info: &mut BoardInfo) {
-    let tmp: u64 = board_info.player_board;
-    board_info.player_board = board_info.opponent_board;
-    board_info.opponent_board = tmp;
-    board_info.now_turn *= TURN_CHANGE_FACTOR;
-}
-
-// 結果取得, 返り値は(黒コマ数，白コマ数，優勢)
-fn get_result(board_info: &BoardInfo) -> (u32, u32, i8) {
-    let mut black_count: u32 = board_info.player_board.count_ones();
-    let mut white_count: u32 = board_info.opponent_board.count_ones();
-    if board_info.now_turn == WHITE {
-        let tmp = black_count;
-        black_count = white_count;
-        white_count = tmp;
-    }
-    let mut superior: i8 = BLACK;
-    if black_count <= white_count {
-        if black_count == white_count {
-            superior = NONE;
-        }else{
-            superior = WHITE;
-        }
-    }
-    return (black_count, white_count, superior);
-}
+use std::sync::Arc;
+use std::time::Instant;
+
+use reversi::board::*;
+use reversi::book::*;
+use reversi::config::*;
+use reversi::engine::*;
+use reversi::eval::*;
+use reversi::ggf::*;
+use reversi::pattern::*;
+use reversi::probcut::*;
+use reversi::random::*;
+use reversi::search::*;
+use reversi::selfplay::*;
+use reversi::transcript::*;
+use reversi::wthor::*;
+use reversi::train::*;
 
 // ゲーム開始
-fn game_start(board_info: &BoardInfo) -> () {
+fn game_start(engine: &Engine, board_info: &BoardInfo) -> () {
     println!("");
     println!("************************");
     println!("");
     println!("GAME START");
     
-    print_board_info(&board_info, evaluate(EVAL_NORMAL, &board_info));
+    print_board_info(&board_info, engine.evaluate(EVAL_NORMAL, &board_info));
 
     println!("");
     println!("************************");
     println!("");
 }
 // ゲーム終了
-fn game_set(board_info: &BoardInfo) -> () {
+fn game_set(engine: &Engine, board_info: &BoardInfo) -> () {
     println!("");
     println!("************************");
     println!("");
     println!("GAME SET");
-    print_board_info(&board_info, evaluate(EVAL_PERFECT, &board_info));
+    print_board_info(&board_info, engine.evaluate(EVAL_PERFECT, &board_info));
 
     println!("");
     println!("************************");
     println!("");
 }
 
-// どの関数で評価するか定める
-fn evaluate(n: i8, board_info: &BoardInfo) -> i32 {
-    return match n {
-        EVAL_BY_POINTTABLE => eval_by_pointtable(board_info),
-        EVAL_NORMAL        => eval_normal(board_info),
-        EVAL_PERFECT       => eval_perfect(board_info),
-        EVAL_WIN           => eval_win(board_info),
-        _                  => panic!("there is not such way of evaluation"),
-    };
+fn read_tcp(reader: &mut BufReader<&TcpStream>) -> Vec<u8> {
+    let mut msg = String::new();
+    reader.read_line(&mut msg).expect("read failure");
+    println!("RECEIVED: {}", msg);
+
+    return Vec::<u8>::from(msg);
 }
 
-// 得点テーブルを用いて盤面を評価する（デバッグ用）
-fn eval_by_pointtable(board_info: &BoardInfo) -> i32 {
-    let mut point: i32 = 0;
-    point += (board_info.player_board & 0x8100000000000081).count_ones() as i32 * (100); // A1
-    point += (board_info.player_board & 0x4281000000008142).count_ones() as i32 * (-50); // B1
-    point += (board_info.player_board & 0x2400810000810024).count_ones() as i32 * (10);  // C1
-    point += (board_info.player_board & 0x0042000000004200).count_ones() as i32 * (-70); // B2
-    point += (board_info.player_board & 0x0024420000422400).count_ones() as i32 * (-5);  // C2
-    point += (board_info.player_board & 0x0018244242241800).count_ones() as i32 * (-10); // D2,C3
-    point += (board_info.player_board & 0x0000182424180000).count_ones() as i32 * (-5);  // D3
-    
-    point -= (board_info.opponent_board & 0x8100000000000081).count_ones() as i32 * (100); // A1
-    point -= (board_info.opponent_board & 0x4281000000008142).count_ones() as i32 * (-50); // B1
-    point -= (board_info.opponent_board & 0x2400810000810024).count_ones() as i32 * (10);  // C1
-    point -= (board_info.opponent_board & 0x0042000000004200).count_ones() as i32 * (-70); // B2
-    point -= (board_info.opponent_board & 0x0024420000422400).count_ones() as i32 * (-5);  // C2
-    point -= (board_info.opponent_board & 0x0018244242241800).count_ones() as i32 * (-10); // D2,C3
-    point -= (board_info.opponent_board & 0x0000182424180000).count_ones() as i32 * (-5);  // D3
-
-    return point;
+fn write_tcp(writer: &mut BufWriter<&TcpStream>, comment: &str) {
+    let msg = format!("{}\n", comment);
+    writer.write_all(msg.as_bytes()).expect("write failure");
+    writer.flush().unwrap();
+    println!("SENT: {}", msg);
 }
 
-// 指定したますの開放度を計算する
-fn count_openness(empty_board: u64, bit: u64) -> i32 {
-    let mut count = 0;
-    if bit & 0x00000000000000ff == 0 { // 下にますがある
-        if (bit >> 8) & empty_board != 0 { // 下が空いている
-            count += 1;
-        }
-    }
-    if bit & 0x80808080808080ff == 0 { // 左下
-        if (bit >> 7) & empty_board != 0 { // 左下
-            count += 1;
-        }
-    }
-    if bit & 0x8080808080808080 == 0 { // 左
-        if (bit << 1) & empty_board != 0 { // 左
-            count += 1;
-        }
-    }
-    if bit & 0xff80808080808080 == 0 { // 左上
-        if (bit << 9) & empty_board != 0 { // 左上
-            count += 1;
-        }
+fn concat(vec: &Vec<u8>) -> i32 {
+    let mut acc: i32 = 0;
+    for i in 0..(vec.len()) {
+        acc *= 10;
+        acc += vec[i] as i32 - 48;
     }
-    if bit & 0xff00000000000000 == 0 { // 上
-        if (bit << 8) & empty_board != 0 { // 上
-            count += 1;
+    return acc;
+}
+
+// STARTを受信した際，自分の色，相手の名前，残りの時間をタプルで返す
+fn read_start(buffer: &Vec<u8>) -> (i8, Vec<char>, i32) { 
+    if buffer[0] == 'S' as u8 {  // STARTがきた 
+        let mut i = 1;
+        while buffer[i] != 'B' as u8 && buffer[i] != 'W' as u8 {
+            i += 1;
         }
-    }
-    if bit & 0xff01010101010101 == 0 { // 右上
-        if (bit << 7) & empty_board != 0 { // 右上
-            count += 1;
+        let my_color: i8;
+        if buffer[i] == 'B' as u8 { // 自分はBLACK
+            my_color = BLACK; // 自分の色を格納する
+        }else{ // 自分はWHITE
+            my_color = WHITE; // 自分の色を格納する
         }
-    }
-    if bit & 0x0101010101010101 == 0 { // 右
-        if (bit >> 1) & empty_board != 0 { // 右
-            count += 1;
+        i += 6;
+        let mut opponent_name = Vec::new();
+        while buffer[i] != ' ' as u8 {
+            opponent_name.push(buffer[i] as char);
+            i += 1;
         }
-    }
-    if bit & 0x01010101010101ff == 0 { // 右下
-        if (bit >> 9) & empty_board != 0 { // 右下
-            count += 1;
+        i += 1;
+        let mut left_time_vec = Vec::new();
+        while buffer[i] != '\n' as u8 {
+            left_time_vec.push(buffer[i]);
+            i += 1;
         }
+        let left_time: i32 = concat(&left_time_vec);
+        return (my_color, opponent_name, left_time);
+    }else{
+        panic!("could not receive 'START'");
     }
-    return count;
 }
 
-// 中盤に用いる評価関数
-fn eval_normal(board_info: &BoardInfo) -> i32 {
-    if board_info.player_board.count_ones() == 0 as u32 {
-        return -100000000;
-    }
-    if board_info.opponent_board.count_ones() == 0 as u32 {
-        return 100000000;
-    }
+// 自己対戦モード: 1局自己対戦させ，棋譜と結果を出力する
+fn selfplay_main(args: &[String]) {
+    let mut time_limit: i32 = 60000;
+    let mut games: usize = 1;
+    let mut random_plies: usize = 0;
+    let mut seed: u64 = 1;
+    let mut corpus: Option<String> = None;
+    let mut record: Option<String> = None;
+    let mut engine = Engine::new();
 
-    let empty_board = !(board_info.player_board | board_info.opponent_board);
-    
-    // ウイング，危険なC打ちをカウント
-    let mut player_wing_count = 0;
-    let mut player_c_place_count = 0;
-    // 上の辺
-    if empty_board & 0x8100000000000000 == 0x8100000000000000 { /* 隅にはなにも置かれていない */
-        if board_info.player_board & 0x3c00000000000000 == 0x3c00000000000000 { // ブロックができている
-            if (board_info.player_board & 0xff00000000000000 == 0x7c00000000000000 && empty_board & 0x0200000000000000 == 0x0200000000000000)
-                || (board_info.player_board & 0xff00000000000000 == 0x3e00000000000000 && empty_board & 0x4000000000000000 == 0x4000000000000000) /* ウイングの形ができている */ {
-                player_wing_count += 1;
-            }
-        }else{
-            player_c_place_count += (board_info.player_board & 0x4200000000000000).count_ones() as i32;
-        }
-    }
-    // 左の辺
-    if empty_board & 0x8000000000000080 == 0x8000000000000080 { /* 隅にはなにも置かれていない */
-        if board_info.player_board & 0x0000808080800000 == 0x0000808080800000 { // ブロックができている
-            if (board_info.player_board & 0x8080808080808080 == 0x0080808080800000 && empty_board & 0x0000000000008000 == 0x0000000000008000)
-                || (board_info.player_board & 0x8080808080808080 == 0x0000808080808000 && empty_board & 0x0080000000000000 == 0x0080000000000000) /* ウイングの形ができている */ {
-                player_wing_count += 1;
-            }
-        }else{
-            player_c_place_count += (board_info.player_board & 0x0080000000008000).count_ones() as i32;
-        }
-    }
-    // 右の辺
-    if empty_board & 0x0100000000000001 == 0x0100000000000001 { /* 隅にはなにも置かれていない */
-        if board_info.player_board & 0x0000010101010000 == 0x0000010101010000 { // ブロックができている
-            if (board_info.player_board & 0x0101010101010101 == 0x0001010101010000 && empty_board & 0x0000000000000100 == 0x0000000000000100)
-                || (board_info.player_board & 0x0101010101010101 == 0x0000010101010100 && empty_board & 0x0001000000000000 == 0x0001000000000000) /* ウイングの形ができている */ {
-                player_wing_count += 1;
-            }
-        }else{
-            player_c_place_count += (board_info.player_board & 0x0001000000000100).count_ones() as i32;
-        }
-    }
-    // 上の辺
-    if empty_board & 0x0000000000000081 == 0x0000000000000081 { /* 隅にはなにも置かれていない */
-        if board_info.player_board & 0x000000000000003c == 0x000000000000003c { // ブロックができている
-            if (board_info.player_board & 0x00000000000000ff == 0x000000000000007c && empty_board & 0x0000000000000002 == 0x0000000000000002)
-                || (board_info.player_board & 0x00000000000000ff == 0x000000000000003e && empty_board & 0x0000000000000040 == 0x0000000000000040) /* ウイングの形ができている */ {
-                player_wing_count += 1;
-            }
-        }else{
-            player_c_place_count += (board_info.player_board & 0x0000000000000042).count_ones() as i32;
-        }
-    }
-    let mut opponent_wing_count = 0;
-    let mut opponent_c_place_count = 0;
-    // 上の辺
-    if empty_board & 0x8100000000000000 == 0x8100000000000000 { /* 隅にはなにも置かれていない */
-        if board_info.opponent_board & 0x3c00000000000000 == 0x3c00000000000000 { // ブロックができている
-            if (board_info.opponent_board & 0xff00000000000000 == 0x7c00000000000000 && empty_board & 0x0200000000000000 == 0x0200000000000000)
-                || (board_info.opponent_board & 0xff00000000000000 == 0x3e00000000000000 && empty_board & 0x4000000000000000 == 0x4000000000000000) /* ウイングの形ができている */ {
-                opponent_wing_count += 1;
-            }
-        }else{
-            opponent_c_place_count += (board_info.opponent_board & 0x4200000000000000).count_ones() as i32;
-        }
-    }
-    // 左の辺
-    if empty_board & 0x8000000000000080 == 0x8000000000000080 { /* 隅にはなにも置かれていない */
-        if board_info.opponent_board & 0x0000808080800000 == 0x0000808080800000 { // ブロックができている
-            if (board_info.opponent_board & 0x8080808080808080 == 0x0080808080800000 && empty_board & 0x0000000000008000 == 0x0000000000008000)
-                || (board_info.opponent_board & 0x8080808080808080 == 0x0000808080808000 && empty_board & 0x0080000000000000 == 0x0080000000000000) /* ウイングの形ができている */ {
-                opponent_wing_count += 1;
-            }
-        }else{
-            opponent_c_place_count += (board_info.opponent_board & 0x0080000000008000).count_ones() as i32;
-        }
-    }
-    // 右の辺
-    if empty_board & 0x0100000000000001 == 0x0100000000000001 { /* 隅にはなにも置かれていない */
-        if board_info.opponent_board & 0x0000010101010000 == 0x0000010101010000 { // ブロックができている
-            if (board_info.opponent_board & 0x0101010101010101 == 0x0001010101010000 && empty_board & 0x0000000000000100 == 0x0000000000000100)
-                || (board_info.opponent_board & 0x0101010101010101 == 0x0000010101010100 && empty_board & 0x0001000000000000 == 0x0001000000000000) /* ウイングの形ができている */ {
-                opponent_wing_count += 1;
-            }
-        }else{
-            opponent_c_place_count += (board_info.opponent_board & 0x0001000000000100).count_ones() as i32;
-        }
-    }
-    // 上の辺
-    if empty_board & 0x0000000000000081 == 0x0000000000000081 { /* 隅にはなにも置かれていない */
-        if board_info.opponent_board & 0x000000000000003c == 0x000000000000003c { // ブロックができている
-            if (board_info.opponent_board & 0x00000000000000ff == 0x000000000000007c && empty_board & 0x0000000000000002 == 0x0000000000000002)
-                || (board_info.opponent_board & 0x00000000000000ff == 0x000000000000003e && empty_board & 0x0000000000000040 == 0x0000000000000040) /* ウイングの形ができている */ {
-                opponent_wing_count += 1;
-            }
-        }else{
-            opponent_c_place_count += (board_info.opponent_board & 0x0000000000000042).count_ones() as i32;
-        }
-    }
-    
-    // 確定石（4隅とそれに連接する石の数）をカウント
-    let mut player_stable_count = 0;
-    if board_info.player_board & 0x8000000000000000 == 0x8000000000000000 { // 左上
-        let mut mask = 0x8000000000000000;
-        let mut tmp_count = 1;
-        for i in 1..8 { // 下へ
-            if board_info.player_board & (mask >> 8*i) == (mask >> 8*i) {
-                tmp_count += 1;
-                continue;
-            }
-            break;
-        }
-        if tmp_count == 8 { // 一列全てが埋まっている場合は重複を考慮してcountを÷2しておく
-            tmp_count = 4;
-        }
-        player_stable_count += tmp_count;
-
-        mask = 0x8000000000000000;
-        tmp_count = 0;
-        for i in 1..8 { // 右へ
-            if board_info.player_board & (mask >> i) == (mask >> i) {
-                tmp_count += 1;
-                continue;
-            }
-            break;
-        }
-        if tmp_count == 7 { // 一列全てが埋まっている場合は重複を考慮してcountを÷2しておく
-            tmp_count = 3;
-        }
-        player_stable_count += tmp_count;
-    }
-    if board_info.player_board & 0x0100000000000000 == 0x0100000000000000 { // 右上
-        let mut mask = 0x0100000000000000;
-        let mut tmp_count = 1;
-        for i in 1..8 { // 下へ
-            if board_info.player_board & (mask >> 8*i) == (mask >> 8*i) {
-                tmp_count += 1;
-                continue;
-            }
-            break;
-        }
-        if tmp_count == 8 { // 一列全てが埋まっている場合は重複を考慮してcountを÷2しておく
-            tmp_count = 4;
-        }
-        player_stable_count += tmp_count;
-
-        mask = 0x0100000000000000;
-        tmp_count = 0;
-        for i in 1..8 { // 左へ
-            if board_info.player_board & (mask << i) == (mask << i) {
-                tmp_count += 1;
-                continue;
-            }
-            break;
-        }
-        if tmp_count == 7 { // 一列全てが埋まっている場合は重複を考慮してcountを÷2しておく
-            tmp_count = 3;
-        }
-        player_stable_count += tmp_count;
-    }
-    if board_info.player_board & 0x0000000000000001 == 0x0000000000000001 { // 右下
-        let mut mask = 0x0000000000000001;
-        let mut tmp_count = 1;
-        for i in 1..8 { // 上へ
-            if board_info.player_board & (mask << 8*i) == (mask << 8*i) {
-                tmp_count += 1;
-                continue;
-            }
-            break;
-        }
-        if tmp_count == 8 { // 一列全てが埋まっている場合は重複を考慮してcountを÷2しておく
-            tmp_count = 4;
-        }
-        player_stable_count += tmp_count;
-
-        mask = 0x0000000000000001;
-        tmp_count = 0;
-        for i in 1..8 { // 左へ
-            if board_info.player_board & (mask << i) == (mask << i) {
-                tmp_count += 1;
-                continue;
-            }
-            break;
-        }
-        if tmp_count == 7 { // 一列全てが埋まっている場合は重複を考慮してcountを÷2しておく
-            tmp_count = 3;
-        }
-        player_stable_count += tmp_count;
-    }
-    if board_info.player_board & 0x0000000000000080 == 0x0000000000000080 { // 左下
-        let mut mask = 0x0000000000000080;
-        let mut tmp_count = 1;
-        for i in 1..8 { // 上へ
-            if board_info.player_board & (mask << 8*i) == (mask << 8*i) {
-                tmp_count += 1;
-                continue;
-            }
-            break;
-        }
-        if tmp_count == 8 { // 一列全てが埋まっている場合は重複を考慮してcountを÷2しておく
-            tmp_count = 4;
-        }
-        player_stable_count += tmp_count;
-
-        mask = 0x0000000000000080;
-        tmp_count = 0;
-        for i in 1..8 { // 右へ
-            if board_info.player_board & (mask >> i) == (mask >> i) {
-                tmp_count += 1;
-                continue;
-            }
-            break;
-        }
-        if tmp_count == 7 { // 一列全てが埋まっている場合は重複を考慮してcountを÷2しておく
-            tmp_count = 3;
-        }
-        player_stable_count += tmp_count;
-    }
-    let mut opponent_stable_count = 0;
-    if board_info.opponent_board & 0x8000000000000000 == 0x8000000000000000 { // 左上
-        let mut mask = 0x8000000000000000;
-        let mut tmp_count = 1;
-        for i in 1..8 { // 下へ
-            if board_info.opponent_board & (mask >> 8*i) == (mask >> 8*i) {
-                tmp_count += 1;
-                continue;
-            }
-            break;
-        }
-        if tmp_count == 8 { // 一列全てが埋まっている場合は重複を考慮してcountを÷2しておく
-            tmp_count = 4;
-        }
-        opponent_stable_count += tmp_count;
-
-        mask = 0x8000000000000000;
-        tmp_count = 0;
-        for i in 1..8 { // 右へ
-            if board_info.opponent_board & (mask >> i) == (mask >> i) {
-                tmp_count += 1;
-                continue;
-            }
-            break;
-        }
-        if tmp_count == 7 { // 一列全てが埋まっている場合は重複を考慮してcountを÷2しておく
-            tmp_count = 3;
-        }
-        opponent_stable_count += tmp_count;
-    }
-    if board_info.opponent_board & 0x0100000000000000 == 0x0100000000000000 { // 右上
-        let mut mask = 0x0100000000000000;
-        let mut tmp_count = 1;
-        for i in 1..8 { // 下へ
-            if board_info.opponent_board & (mask >> 8*i) == (mask >> 8*i) {
-                tmp_count += 1;
-                continue;
-            }
-            break;
-        }
-        if tmp_count == 8 { // 一列全てが埋まっている場合は重複を考慮してcountを÷2しておく
-            tmp_count = 4;
-        }
-        opponent_stable_count += tmp_count;
-
-        mask = 0x0100000000000000;
-        tmp_count = 0;
-        for i in 1..8 { // 左へ
-            if board_info.opponent_board & (mask << i) == (mask << i) {
-                tmp_count += 1;
-                continue;
-            }
-            break;
-        }
-        if tmp_count == 7 { // 一列全てが埋まっている場合は重複を考慮してcountを÷2しておく
-            tmp_count = 3;
-        }
-        opponent_stable_count += tmp_count;
-    }
-    if board_info.opponent_board & 0x0000000000000001 == 0x0000000000000001 { // 右下
-        let mut mask = 0x0000000000000001;
-        let mut tmp_count = 1;
-        for i in 1..8 { // 上へ
-            if board_info.opponent_board & (mask << 8*i) == (mask << 8*i) {
-                tmp_count += 1;
-                continue;
-            }
-            break;
-        }
-        if tmp_count == 8 { // 一列全てが埋まっている場合は重複を考慮してcountを÷2しておく
-            tmp_count = 4;
-        }
-        opponent_stable_count += tmp_count;
-
-        mask = 0x0000000000000001;
-        tmp_count = 0;
-        for i in 1..8 { // 左へ
-            if board_info.opponent_board & (mask << i) == (mask << i) {
-                tmp_count += 1;
-                continue;
-            }
-            break;
-        }
-        if tmp_count == 7 { // 一列全てが埋まっている場合は重複を考慮してcountを÷2しておく
-            tmp_count = 3;
+    let mut i = 0;
+    while i < args.len() {
+        if args[i] == "-t" { // 持ち時間（ms）
+            time_limit = args[i+1].parse().expect("INVALID time");
+            i += 2;
+        }else if parse_engine_flag(&mut engine, args, i) {
+            i += 2;
+        }else if args[i] == "-g" { // 対局数
+            games = args[i+1].parse().expect("INVALID number of games");
+            i += 2;
+        }else if args[i] == "-r" { // 初めにランダムに打つ手数
+            random_plies = args[i+1].parse().expect("INVALID number of plies");
+            i += 2;
+        }else if args[i] == "-s" { // 乱数の種
+            seed = args[i+1].parse().expect("INVALID seed");
+            i += 2;
+        }else if args[i] == "-c" { // 各局面と最終石差を書き足す学習データのファイル
+            corpus = Some(args[i+1].clone());
+            i += 2;
+        }else if args[i] == "-R" { // 棋譜を書き足すファイル
+            record = Some(args[i+1].clone());
+            i += 2;
+        }else {
+            panic!("INVALID args");
         }
-        opponent_stable_count += tmp_count;
     }
-    if board_info.opponent_board & 0x0000000000000080 == 0x0000000000000080 { // 左下
-        let mut mask = 0x0000000000000080;
-        let mut tmp_count = 1;
-        for i in 1..8 { // 上へ
-            if board_info.opponent_board & (mask << 8*i) == (mask << 8*i) {
-                tmp_count += 1;
-                continue;
-            }
-            break;
-        }
-        if tmp_count == 8 { // 一列全てが埋まっている場合は重複を考慮してcountを÷2しておく
-            tmp_count = 4;
-        }
-        opponent_stable_count += tmp_count;
-
-        mask = 0x0000000000000080;
-        tmp_count = 0;
-        for i in 1..8 { // 右へ
-            if board_info.opponent_board & (mask >> i) == (mask >> i) {
-                tmp_count += 1;
-                continue;
-            }
-            break;
+
+    print_engine(&engine);
+    let engine = Arc::new(engine);
+
+    let mut rng = Rng::new(seed);
+    for _ in 0..games {
+        let (start, opening) = random_opening(random_plies, &mut rng);
+        let game_record = selfplay(&engine, &start, time_limit);
+        print_game_record(&opening, &game_record);
+        if let Some(path) = &corpus {
+            append_corpus(path, &samples_from_record(&start, &game_record)).unwrap();
         }
-        if tmp_count == 7 { // 一列全てが埋まっている場合は重複を考慮してcountを÷2しておく
-            tmp_count = 3;
+        if let Some(path) = &record {
+            let moves: Vec<u64> = opening.iter().cloned().chain(game_record.moves.iter().map(|mov| mov.place_bit)).collect();
+            record_game(path, &moves);
         }
-        opponent_stable_count += tmp_count;
     }
+}
 
-    // 危険なX打ちの数
-    let mut player_x_place_count = 0;
-    if board_info.player_board & 0x0040000000000000 == 0x0040000000000000 && empty_board & 0x8000000000000000 == 0x8000000000000000 { // 左上
-        player_x_place_count += 1;
-    }
-    if board_info.player_board & 0x0002000000000000 == 0x0002000000000000 && empty_board & 0x0100000000000000 == 0x0100000000000000 { // 右上
-        player_x_place_count += 1;
-    }
-    if board_info.player_board & 0x0000000000000200 == 0x0000000000000200 && empty_board & 0x0000000000000001 == 0x0000000000000001 { // 右下
-        player_x_place_count += 1;
-    }
-    if board_info.player_board & 0x0000000000004000 == 0x0000000000004000 && empty_board & 0x0000000000000080 == 0x0000000000000080 { // 左下
-        player_x_place_count += 1;
-    }
+// 自己対戦の記録を出力する（openingは記録の前にランダムに打った手）
+fn print_game_record(opening: &[u64], record: &GameRecord) {
+    println!("");
+    if !opening.is_empty() {
+        println!("OPENING: {}", line_to_string(opening));
+    }
+    for (i, mov) in record.moves.iter().enumerate() {
+        println!("{:>2} {} {:<4} eval={:>6} way_of_eval={:>2} {}ms pv={}",
+            opening.len() + i + 1,
+            if mov.now_turn == BLACK {"BLACK"} else {"WHITE"},
+            move_to_string(mov.place_bit),
+            mov.eval,
+            mov.way_of_eval,
+            mov.elapsed_ms,
+            line_to_string(&mov.pv),
+        );
+    }
+    let move_list: Vec<u64> = opening.iter().cloned().chain(record.moves.iter().map(|mov| mov.place_bit)).collect();
+    println!("MOVES: {}", line_to_string(&move_list));
+    println!("RESULT: BLACK:{}, WHITE:{}, WINNER:{}", record.black_count, record.white_count, match record.superior {
+        BLACK => "BLACK",
+        WHITE => "WHITE",
+        _     => "NONE",
+    });
+}
 
-    let mut opponent_x_place_count = 0;
-    if board_info.opponent_board & 0x0040000000000000 == 0x0040000000000000 && empty_board & 0x8000000000000000 == 0x8000000000000000 { // 左上
-        opponent_x_place_count += 1;
-    }
-    if board_info.opponent_board & 0x0002000000000000 == 0x0002000000000000 && empty_board & 0x0100000000000000 == 0x0100000000000000 { // 右上
-        opponent_x_place_count += 1;
-    }
-    if board_info.opponent_board & 0x0000000000000200 == 0x0000000000000200 && empty_board & 0x0000000000000001 == 0x0000000000000001 { // 右下
-        opponent_x_place_count += 1;
-    }
-    if board_info.opponent_board & 0x0000000000004000 == 0x0000000000004000 && empty_board & 0x0000000000000080 == 0x0000000000000080 { // 左下
-        opponent_x_place_count += 1;
-    }
+// 相手の手で終局したときはその手がMOVEで送られてこないので，ENDの石数（"END WIN 自分の石数 相手の石数 DOUBLE_PASS"）に合う手を探す
+fn find_last_move(board_info: &BoardInfo, my_color: i8, buffer: &[u8]) -> Option<u64> {
+    let text = String::from_utf8_lossy(buffer);
+    let words: Vec<&str> = text.split_whitespace().collect();
+    if words.len() != 5 || words[4] != "DOUBLE_PASS" {
+        return None;
+    }
+    let my_count: u32 = words[2].parse().ok()?;
+    let opponent_count: u32 = words[3].parse().ok()?;
+    let (black_count, white_count) = if my_color == BLACK {(my_count, opponent_count)} else {(opponent_count, my_count)};
+    let mut legal_board = make_legal_board(board_info);
+    while legal_board != 0 {
+        let mask = legal_board & legal_board.wrapping_neg();
+        legal_board ^= mask;
+        let mut tmp_board_info = board_info.clone();
+        place(mask, &mut tmp_board_info);
+        swap(&mut tmp_board_info);
+        let (black, white, _) = get_result(&tmp_board_info);
+        if is_game_over(&tmp_board_info) && black == black_count && white == white_count {
+            return Some(mask);
+        }
+    }
+    return None;
+}
 
-    // 着手可能手数
-    let player_legal_board_count = make_legal_board(&board_info).count_ones() as i32;
-    let mut tmp_board_info = board_info.clone();
-    swap(&mut tmp_board_info);
-    let opponent_legal_board_count = make_legal_board(&tmp_board_info).count_ones() as i32;
-
-    // 開放度計算
-    let mut player_openness = 0;
-    let mut opponent_openness = 0;
-    let mut mask: u64 = 0x8000000000000000;
-    for _ in 0..BOARDSIZE {
-        if board_info.player_board & mask != 0 {
-            player_openness += count_openness(empty_board, mask);
-        }else if board_info.opponent_board & mask != 0 {
-            opponent_openness += count_openness(empty_board, mask);
-        }
-        mask = mask >> 1;
-    }
+// 初期局面からの手順movesを棋譜としてファイルに書き足す
+fn record_game(path: &str, moves: &[u64]) {
+    match Transcript::from_moves(moves).and_then(|transcript| append_transcript(path, &transcript)) {
+        Ok(()) => println!("recorded the game to {}", path),
+        Err(e) => println!("could not record the game: {}", e),
+    };
+}
 
-    return (player_stable_count - opponent_stable_count) * WEIGHT_STABLE
-        + (player_wing_count - opponent_wing_count) * WEIGHT_WING
-        + (player_x_place_count - opponent_x_place_count) * WEIGHT_XMOVE
-        + (player_c_place_count - opponent_c_place_count) * WEIGHT_CMOVE
-        + (player_legal_board_count - opponent_legal_board_count) * WEIGHT_MOBILITY
-        + (player_openness - opponent_openness) * WEIGHT_OPENNESS;
+// パターンの重みファイルを読み込み，engineの中盤の評価関数をEVAL_PATTERNにする
+fn load_pattern_weights(engine: &mut Engine, path: &str) {
+    match PatternWeights::load(path) {
+        Ok(weights) => engine.set_pattern_weights(weights),
+        Err(e) => panic!("could not load pattern weights: {}", e),
+    };
 }
 
-// 完全読み切り（個数も読む）
-fn eval_perfect(board_info: &BoardInfo) -> i32 {
-    return board_info.player_board.count_ones() as i32 - board_info.opponent_board.count_ones() as i32;
+// 設定ファイルを読み込んでconfigを上書きする
+fn load_config(config: &mut Config, path: &str) {
+    if let Err(e) = config.load(path) {
+        panic!("could not load config: {}", e);
+    }
 }
 
-// 勝つかどうかだけ読む（個数は読まない）
-fn eval_win(board_info: &BoardInfo) -> i32 {
-    let diff = board_info.player_board.count_ones() as i32 - board_info.opponent_board.count_ones() as i32;
-    return if diff > 0 {1} else if diff == 0 {0} else {-1};
+// 設定についての共通のオプション（-C，-D）ならargs[i+1]を値として適用してtrueを返す
+fn parse_config_flag(config: &mut Config, args: &[String], i: usize) -> bool {
+    if args[i] == "-C" { // 設定ファイル（eval_normalの重みファイルも同じ形式）
+        load_config(config, &args[i+1]);
+    }else if args[i] == "-D" { // 設定を1つ上書きする（NAME=VALUE）
+        config.set_assignment(&args[i+1]).unwrap_or_else(|e| panic!("INVALID setting: {}", e));
+    }else {
+        return false;
+    }
+    return true;
 }
 
-// 探索（alpha-beta法による）
-fn negamax(alpha_: i32, beta_: i32, limit: i8, board_info: &mut BoardInfo, way_of_eval: i8) -> i32 {
-    let mut alpha: i32 = alpha_;
-    let beta: i32 = beta_;
+// エンジンについての共通のオプション（-T，-m，-b，-w，-C，-D）ならargs[i+1]を値として適用してtrueを返す
+// クライアントと各サブコマンドで同じものを受け付ける
+fn parse_engine_flag(engine: &mut Engine, args: &[String], i: usize) -> bool {
+    if args[i] == "-T" { // 探索に使うスレッド数
+        engine.set_thread_count(args[i+1].parse().expect("INVALID number of threads"));
+    }else if args[i] == "-m" { // ProbCutのパラメータファイル
+        load_mpc_params(engine, &args[i+1]);
+    }else if args[i] == "-b" { // 定石ファイル
+        load_book(engine, &args[i+1]);
+    }else if args[i] == "-w" { // パターンの重みファイル（中盤の評価関数をEVAL_PATTERNにする）
+        load_pattern_weights(engine, &args[i+1]);
+    }else {
+        return parse_config_flag(&mut engine.config, args, i);
+    }
+    return true;
+}
 
-    if limit == 0 || is_game_over(board_info) { // 深さ制限 or 終局
-        return evaluate(way_of_eval, &board_info);
+// 探索に用いる設定を出力する
+fn print_config(config: &Config) {
+    println!("config:");
+    for line in config.to_text().lines() {
+        println!("  {}", line);
     }
+}
 
-    let legal_board: u64 = make_legal_board(&board_info);
-    let mut score: i32;
-
-    if legal_board.count_ones() == 0 as u32 { // パス
-        let tmp_board_info: BoardInfo = board_info.clone();
-        swap(board_info);
-        score = -negamax(-beta, -alpha, limit, board_info, way_of_eval); // さらに奥を深さを変えずに探索
-        *board_info = tmp_board_info; // 盤面を元に戻す
-        return score;
+// エンジンの設定と，中盤の探索でProbCutを行うかを出力する
+fn print_engine(engine: &Engine) {
+    print_config(&engine.config);
+    let evaluator = engine.midgame_evaluator();
+    if engine.mpc_params(evaluator).is_some() {
+        println!("ProbCut: on (way_of_eval={})", evaluator);
+    }else{
+        println!("ProbCut: off (no parameters fitted for way_of_eval={} with these weights)", evaluator);
     }
+}
+
+// 学習モード: 学習データから評価関数の重みを求めてファイルに書き出す
+fn train_main(args: &[String]) {
+    let mut input: Option<String> = None;
+    let mut evaluator = "normal".to_string();
+    let mut output: Option<String> = None;
+    let mut epochs: usize = 100;
+    let mut rate: f32 = 0.01;
+    let mut config = Config::new();
 
-    let mut score_max: i32 = std::i32::MIN;
-    let mut mask: u64 = 0x0000000000000001;
-
-    for _ in 0..BOARDSIZE {
-        if mask & legal_board != 0 { // maskが実際における場所であるとき
-            let tmp_board_info: BoardInfo = board_info.clone();
-            place(mask, board_info); // 実際においてみる
-            swap(board_info);
-            score = -negamax(-beta, -alpha, limit-1, board_info, way_of_eval);
-            *board_info = tmp_board_info; // 盤面を元に戻す
-
-            if score >= beta { // βカット
-                return score;
-            }
-            if score > score_max { // 得点が高くなるように更新
-                score_max = score;
-                alpha = max(alpha, score_max); // α値更新
-            }
+    let mut i = 0;
+    while i < args.len() {
+        if args[i] == "-i" { // 学習データのファイル（拡張子が.wtbならWTHORのデータベース）
+            input = Some(args[i+1].clone());
+            i += 2;
+        }else if args[i] == "-e" { // 重みを求める評価関数（normalかpattern）
+            evaluator = args[i+1].clone();
+            i += 2;
+        }else if args[i] == "-o" { // 出力ファイル
+            output = Some(args[i+1].clone());
+            i += 2;
+        }else if args[i] == "-n" { // 勾配降下法の繰り返し回数
+            epochs = args[i+1].parse().expect("INVALID number of epochs");
+            i += 2;
+        }else if args[i] == "-r" { // 勾配降下法の学習率
+            rate = args[i+1].parse().expect("INVALID rate");
+            i += 2;
+        }else if parse_config_flag(&mut config, args, i) {
+            i += 2;
+        }else {
+            panic!("INVALID args");
         }
-        mask = mask << 1;
     }
 
-    return score_max;
+    print_config(&config);
+
+    let input = input.expect("specify the corpus with -i");
+    let samples = if input.ends_with(".wtb") {
+        let database = load_wthor(&input).unwrap();
+        let (samples, invalid) = wthor_samples(&database);
+        println!("loaded {} games from {} ({} could not be replayed)", database.games.len(), input, invalid);
+        samples
+    }else{
+        load_corpus(&input).unwrap()
+    };
+    println!("loaded {} positions", samples.len());
+    let text = if evaluator == "normal" {
+        let weights = train_normal(&samples, &config).unwrap();
+        normal_weights_to_text(&weights)
+    }else if evaluator == "pattern" {
+        train_pattern(&samples, epochs, rate).to_text()
+    }else{
+        panic!("INVALID evaluator: {}", evaluator);
+    };
+    let output = output.unwrap_or(format!("{}.txt", evaluator));
+    std::fs::write(&output, text).expect("could not write weights");
+    println!("wrote {} weights to {}", evaluator, output);
 }
 
-// 着手する手を思考する
-fn decide(board_info: &mut BoardInfo, left_time: i32, way_of_eval: i8, limit: i8) -> u64 {
-    let legal_board: u64 = make_legal_board(&board_info);
+// 定石ファイルを読み込んでengineの対局に用いる
+fn load_book(engine: &mut Engine, path: &str) {
+    match Book::load(path) {
+        Ok(book) => engine.book = book,
+        Err(e) => panic!("could not load book: {}", e),
+    };
+}
 
-    if legal_board == 0 as u64 { // おける手がなければパスを選択
-        return 0 as u64;
+// 定石を作るモード: import（定石の手順のリストから定石ファイルを作る），build（探索して定石ファイルを広げる）
+fn book_main(args: &[String]) {
+    if args.is_empty() {
+        panic!("INVALID book command: specify import or build");
+    }
+    if args[0] == "import" {
+        book_import_main(&args[1..]);
+    }else if args[0] == "build" {
+        book_build_main(&args[1..]);
+    }else{
+        panic!("INVALID book command: {}", args[0]);
     }
+}
+
+// 定石の手順のリストから定石ファイルを作る
+fn book_import_main(args: &[String]) {
+    let mut input: Option<String> = None;
+    let mut output = "book.txt".to_string();
+    let mut max_plies: usize = MAX_TURNS as usize;
 
-    if legal_board.count_ones() == 1 as u32 { // おける手が一つしかなければそのままそれを返す
-        return legal_board;
+    let mut i = 0;
+    while i < args.len() {
+        if args[i] == "-i" { // 定石の手順のリスト（1行に"F5 D6 C3"のような手順を1つ），拡張子が.ggfならGGFの棋譜，.wtbならWTHORのデータベース
+            input = Some(args[i+1].clone());
+            i += 2;
+        }else if args[i] == "-o" { // 出力ファイル
+            output = args[i+1].clone();
+            i += 2;
+        }else if args[i] == "-p" { // 棋譜（.ggf，.wtb）の初めから何手までを定石に加えるか
+            max_plies = args[i+1].parse().expect("INVALID number of plies");
+            i += 2;
+        }else {
+            panic!("INVALID args");
+        }
     }
 
-    let mut ret: u64 = 0;
+    let input = input.expect("specify the list of lines with -i");
+    let book = if input.ends_with(".ggf") {
+        // 初期局面から始まる棋譜の手順をすべて定石に加える
+        let games = load_ggf(&input).unwrap_or_else(|e| panic!("could not import {}: {}", input, e));
+        let mut book = Book::new();
+        for game in games.iter().filter(|game| game.starts_from_initial_position()) {
+            let line = game.line();
+            book.add_line(&line[..line.len().min(max_plies)]).unwrap();
+        }
+        book
+    }else if input.ends_with(".wtb") {
+        let database = load_wthor(&input).unwrap_or_else(|e| panic!("could not import {}: {}", input, e));
+        let mut book = Book::new();
+        let mut invalid = 0;
+        for game in database.games.iter() {
+            match game.replay() {
+                Ok(transcript) => book.add_line(&transcript.moves[..transcript.moves.len().min(max_plies)]).unwrap(),
+                Err(_) => invalid += 1,
+            };
+        }
+        println!("skipped {} games that could not be replayed", invalid);
+        book
+    }else{
+        let text = std::fs::read_to_string(&input).unwrap_or_else(|e| panic!("could not read {}: {}", input, e));
+        Book::from_lines(&text).unwrap_or_else(|e| panic!("could not import {}: {}", input, e))
+    };
+    std::fs::write(&output, book.to_text()).expect("could not write book");
+    println!("wrote {} positions to {}", book.len(), output);
+}
 
-    // 実行速度計測開始
-    let start = Instant::now();
+// 定石ファイルの末端の局面を探索して評価値をつけ，negamaxで伝えながら広げて，1局面広げるごとに保存する
+// 定石ファイルがなければ初期局面から作る，中断しても同じコマンドで続きから広げられる
+fn book_build_main(args: &[String]) {
+    let mut path = "book.txt".to_string();
+    let mut expansions: usize = 100;
+    let mut depth: i8 = 8;
+    let mut max_plies: i32 = 20;
+    let mut margin: i32 = 200;
+    let mut engine = Engine::new();
 
-    println!("debug: left_time={}, way_of_eval={}, limit={}", left_time, way_of_eval, limit);
-
-    // 作戦: alpha-beta法を用いた探索
-    let mut mask: u64 = 0x0000000000000001;
-    let mut max_eval: i32 = std::i32::MIN;
-
-    // マルチスレッドによる実装
-    let mut thread_count = 0;
-    let mut threads = Vec::new();
-    let mut receiver = Vec::new();
-
-    for _ in 0..BOARDSIZE {
-        if mask & legal_board != 0 { // maskが実際における場所であるとき
-            thread_count += 1;
-            // メインスレッド -> サブスレッドのチャンネル
-            let (s1, r1) = mpsc::channel();
-            // サブスレッド -> メインスレッドのチャンネル
-            let (s2, r2) = mpsc::channel();
-            threads.push(thread::spawn(move || {
-                // メインスレッドから情報が送られてくる
-                let (mask, limit, mut tmp_board_info, way_of_eval) = r1.recv().unwrap();
-                place(mask, &mut tmp_board_info);
-                swap(&mut tmp_board_info);
-                let tmp = -negamax(std::i32::MIN+1, std::i32::MAX-1, limit-1, &mut tmp_board_info, way_of_eval); // int_maxやint_minをnegateするとoverflowが発生するため，値を調節している
-                let (c1, c2) = bit_to_point(mask);
-                let mov = vec![c1, c2];
-                let mov_string: String = mov.iter().collect();
-                match s2.send((mask, tmp)) {
-                    Ok(_) => println!("debug: score={}, place={}", tmp, mov_string),
-                    Err(mpsc::SendError(_)) => println!("debug: this thread is not useless; ignore it."), // すでに計算が不要でいらないthread
-                };
-            }));
-            s1.send((mask, limit, board_info.clone(), way_of_eval)).unwrap();
-            receiver.push(r2);
+    let mut i = 0;
+    while i < args.len() {
+        if args[i] == "-b" { // 定石ファイル（読み込んで広げ，同じファイルに保存する）
+            path = args[i+1].clone();
+            i += 2;
+        }else if args[i] == "-n" { // 広げる局面の数
+            expansions = args[i+1].parse().expect("INVALID number of expansions");
+            i += 2;
+        }else if args[i] == "-d" { // 探索の深さ
+            depth = args[i+1].parse().expect("INVALID depth");
+            i += 2;
+        }else if args[i] == "-p" { // 定石に加える局面の手数の上限
+            max_plies = args[i+1].parse().expect("INVALID number of plies");
+            i += 2;
+        }else if args[i] == "-e" { // 定石に加える手の，最善の評価値との差の上限（評価値の単位）
+            margin = args[i+1].parse().expect("INVALID margin");
+            i += 2;
+        }else if parse_engine_flag(&mut engine, args, i) {
+            i += 2;
+        }else {
+            panic!("INVALID args");
         }
-        mask = mask << 1;
     }
 
-    // 集計
-    let mut finished_thread_count = 0;
-    let mut i = 0;
-    while finished_thread_count != thread_count {
-        match receiver[i].try_recv() { // busy loopでスレッドを順にみていく
-            Ok((bit_ok, tmp_ok)) => {
-                finished_thread_count += 1;
-                let (bit, tmp) = (bit_ok, tmp_ok);
-
-                if way_of_eval == EVAL_WIN { // 必勝読みでは，必勝できる手が見つかったら探索を打ち切ってその手を打つ
-                    if tmp == 1 { // 必勝できる手が見つかった
-                        println!("Win-Road found: stop searching");
-                        return bit;
-                        // 注意：このときまだ動き続けているスレッドがあるので，Err(mpsc::SendError(_))として別処理が必要
-                    }
-                }
+    print_engine(&engine);
+    let engine = Arc::new(engine);
 
-                // 得点が高くなるように更新
-                if tmp > max_eval { 
-                    max_eval = tmp;
-                    ret = bit;
-                }
-            },
-            Err(mpsc::TryRecvError::Empty) => {
-                let elapse = start.elapsed();
-                let sec = elapse.as_secs();
-                // 必勝読みや完全読みの境目にいるとき，残り10秒以下ならやばくなってくるので，現状で最も良い結果を返す
-                if (way_of_eval == EVAL_WIN || way_of_eval == EVAL_PERFECT) && left_time - sec as i32 * 1000 <= 10000 && 14 <= (60 - board_info.now_index) && (60 - board_info.now_index) <= EVAL_WIN_DEPTH { 
-                    return decide(board_info, left_time, EVAL_NORMAL, EVAL_NORMAL_DEPTH - 2);
-                    // 注意：このときまだ動き続けているスレッドがあるので，Err(mpsc::SendError(_))として別処理が必要
-                }
-                if way_of_eval == EVAL_NORMAL && sec as i32 >= 3 && left_time - sec as i32 * 1000 <= 40000 { // EVAL_NORMALにおいて3秒かかっているときは探索打ち切り
-                    if ret == 0 {
-                        let legal_board: u64 = make_legal_board(board_info);
-                            if legal_board == 0 as u64 { // おける手がなければパスを選択
-                                ret = 0;
-                            }else{
-                                let mut mask = 0x800000000000000;
-                                for _ in 0..BOARDSIZE {
-                                    if mask & legal_board != 0 {
-                                        ret = mask;
-                                        break;
-                                    }
-                                    mask = mask >> 1;
-                                }
-                            }
-                    }
-                    return ret;
-                }
-                continue;
+    let mut book = if std::path::Path::new(&path).exists() {
+        Book::load(&path).unwrap_or_else(|e| panic!("could not load book: {}", e))
+    }else{
+        Book::new()
+    };
+    println!("loaded {} positions from {}", book.len(), path);
+
+    let shared = SearchShared::new(&engine, engine.thread_count());
+    let count = book.evaluate_leaves(depth, &shared);
+    book.propagate();
+    if count > 0 {
+        println!("evaluated {} leaf moves", count);
+        std::fs::write(&path, book.to_text()).expect("could not write book");
+    }
+    for n in 0..expansions {
+        let board_info = match book.expand(depth, max_plies, margin, &shared) {
+            Some(board_info) => board_info,
+            None => {
+                println!("no more positions to expand");
+                break;
             },
-            _ => panic!("Non-expected error occured"),
-        };  
-        i += 1;
-        if i == thread_count {
-            i = 0;
+        };
+        book.propagate();
+        std::fs::write(&path, book.to_text()).expect("could not write book");
+        let moves: Vec<String> = book.lookup(&board_info).iter()
+            .map(|m| format!("{}({})", move_to_string(m.place_bit), m.score.map_or("-".to_string(), |score| score.to_string()))).collect();
+        println!("expanded {}/{}: {:016x} {:016x} {}", n + 1, expansions, board_info.player_board, board_info.opponent_board, moves.join(" "));
+    }
+    println!("wrote {} positions to {}", book.len(), path);
+}
+
+// 局面の検討モード: 局面の文字列（board_info_to_stringの形式）を受け取り，全合法手の評価値と読み筋を良い順に出力する
+fn analyze_main(args: &[String]) {
+    let mut position = Vec::<String>::new();
+    let mut depth: Option<i8> = None;
+    let mut time_limit: u64 = 10000;
+    let mut engine = Engine::new();
+
+    let mut i = 0;
+    while i < args.len() {
+        if args[i] == "-d" { // 探索の深さ（省略時は設定の深さ）
+            depth = Some(args[i+1].parse().expect("INVALID depth"));
+            i += 2;
+        }else if args[i] == "-t" { // 探索時間（ms）
+            time_limit = args[i+1].parse().expect("INVALID time");
+            i += 2;
+        }else if parse_engine_flag(&mut engine, args, i) {
+            i += 2;
+        }else if args[i].len() == 2 && args[i].starts_with('-') {
+            panic!("INVALID args");
+        }else{ // 局面（手番の前の空白で分かれていてもよい）
+            position.push(args[i].clone());
+            i += 1;
         }
     }
-    for thread in threads {
-        thread.join().unwrap();
+
+    print_engine(&engine);
+    let engine = Arc::new(engine);
+
+    let board_info = string_to_board_info(&position.join(" ")).unwrap_or_else(|e| panic!("INVALID position: {}", e));
+    let way_of_eval = choose_evaluator(&engine, &board_info);
+    let limit = depth.unwrap_or_else(|| choose_limit(&engine, way_of_eval));
+    print_board_info(&board_info, engine.evaluate(way_of_eval, &board_info));
+
+    let start = Instant::now();
+    let (searched_depth, analyses) = match analyze(&engine, &board_info, way_of_eval, limit, time_limit) {
+        Some(result) => result,
+        None => {
+            println!("timed out after {}ms before finishing any depth", time_limit);
+            return ();
+        },
+    };
+    if analyses.is_empty() {
+        println!("the game is over: no legal moves");
+        return ();
+    }
+    println!("way_of_eval={}, depth={}, {}ms", way_of_eval, searched_depth, start.elapsed().as_millis());
+    println!("rank move  score  pv");
+    for (rank, analysis) in analyses.iter().enumerate() {
+        println!("{:>4} {:<4} {:>6}  {}", rank + 1, move_to_string(analysis.place_bit), score_to_string(way_of_eval, analysis.score), line_to_string(&analysis.pv));
     }
+}
 
-    // 勝敗予想
+// 評価値を表示用の文字列にする（必勝読みなら勝敗，完全読みなら石差，パターンの評価関数なら評価値を石差に直したもの）
+// eval_normalの重みは石差の単位とは限らない（学習で求めたものでなければ手で決めたもの）ので，その評価値はそのまま表示する
+fn score_to_string(way_of_eval: i8, score: i32) -> String {
     if way_of_eval == EVAL_WIN {
-        match max_eval {
-            1  => println!("I predict I will win."),
-            0  => println!("I predict this game will end in a draw."),
-            -1 => println!("I predict I will lose."),
-            _  => panic!("Invalid value"),
+        return match score.signum() {
+            1 => "WIN".to_string(),
+            0 => "DRAW".to_string(),
+            _ => "LOSS".to_string(),
         };
-    }else if way_of_eval == EVAL_PERFECT {
-        if max_eval > 0 {
-            println!("I predict I will win by {} points.", max_eval);
-        }else if max_eval == 0 {
-            println!("I predict this game will end in a draw.");
-        }else{
-            println!("I predict I will lose by {} points.", -max_eval);
-        }
     }
+    if way_of_eval == EVAL_PERFECT {
+        return format!("{:+}", score);
+    }
+    if way_of_eval == EVAL_PATTERN {
+        return format!("{:+.2}", score as f32 / PATTERN_SCALE);
+    }
+    return format!("{:+}", score);
+}
 
-    return ret;
+// ProbCutのパラメータファイルを読み込んでengineの探索に用いる
+fn load_mpc_params(engine: &mut Engine, path: &str) {
+    match MpcParams::load(path) {
+        Ok(params) => engine.set_mpc_params(params),
+        Err(e) => panic!("could not load ProbCut parameters: {}", e),
+    };
 }
 
-// board_infoの状況に合わせて適切なevaluatorを選択する
-fn choose_evaluator(board_info: &BoardInfo) -> i8 {
-    if MAX_TURNS - board_info.now_index <= EVAL_WIN_DEPTH {
-        if MAX_TURNS - board_info.now_index <= EVAL_PERFECT_DEPTH {
-            return EVAL_PERFECT;
+// ProbCutのパラメータを求めるモード: 自己対戦で局面を集め，浅い探索と深い探索の評価値の関係をファイルに書き出す
+fn mpc_main(args: &[String]) {
+    let mut games: usize = 20;
+    let mut max_depth: i8 = 10;
+    let mut seed: u64 = 1;
+    let mut output = "mpc.txt".to_string();
+    let mut engine = Engine::new();
+
+    let mut i = 0;
+    while i < args.len() {
+        if args[i] == "-g" { // 局面を集める自己対戦の対局数
+            games = args[i+1].parse().expect("INVALID number of games");
+            i += 2;
+        }else if args[i] == "-d" { // 求める深さの上限
+            max_depth = args[i+1].parse().expect("INVALID depth");
+            i += 2;
+        }else if args[i] == "-s" { // 乱数の種
+            seed = args[i+1].parse().expect("INVALID seed");
+            i += 2;
+        }else if args[i] == "-o" { // 出力ファイル
+            output = args[i+1].clone();
+            i += 2;
+        }else if parse_engine_flag(&mut engine, args, i) {
+            i += 2;
+        }else {
+            panic!("INVALID args");
         }
-        return EVAL_WIN;
     }
-    return EVAL_NORMAL;
-}
 
-fn read_tcp(reader: &mut BufReader<&TcpStream>) -> Vec<u8> {
-    let mut msg = String::new();
-    reader.read_line(&mut msg).expect("read failure");
-    println!("RECEIVED: {}", msg);
+    print_engine(&engine);
+    let engine = Arc::new(engine);
 
-    return Vec::<u8>::from(msg);
+    let positions = collect_positions(&engine, games, seed);
+    println!("collected {} positions from {} games", positions.len(), games);
+    let params = fit_mpc_params(&engine, &positions, max_depth);
+    std::fs::write(&output, params.to_text()).expect("could not write ProbCut parameters");
+    println!("wrote ProbCut parameters to {}", output);
 }
 
-fn write_tcp(writer: &mut BufWriter<&TcpStream>, comment: &str) {
-    let msg = format!("{}\n", comment);
-    writer.write(msg.as_bytes()).expect("write failure");
-    writer.flush().unwrap();
-    println!("SENT: {}", msg);
-}
+fn main() {
+    let args: Vec<String> = env::args().collect();
 
-fn concat(vec: &Vec<u8>) -> i32 {
-    let mut acc: i32 = 0;
-    for i in 0..(vec.len()) {
-        acc *= 10;
-        acc += vec[i] as i32 - 48;
+    if args.len() >= 2 && args[1] == "selfplay" { // 自己対戦モード
+        selfplay_main(&args[2..]);
+        return ();
     }
-    return acc;
-}
 
-// STARTを受信した際，自分の色，相手の名前，残りの時間をタプルで返す
-fn read_start(buffer: &Vec<u8>) -> (i8, Vec<char>, i32) { 
-    if buffer[0] == 'S' as u8 {  // STARTがきた 
-        let mut i = 1;
-        while buffer[i] != 'B' as u8 && buffer[i] != 'W' as u8 {
-            i += 1;
-        }
-        let my_color: i8;
-        if buffer[i] == 'B' as u8 { // 自分はBLACK
-            my_color = BLACK; // 自分の色を格納する
-        }else{ // 自分はWHITE
-            my_color = WHITE; // 自分の色を格納する
-        }
-        i += 6;
-        let mut opponent_name = Vec::new();
-        while buffer[i] != ' ' as u8 {
-            opponent_name.push(buffer[i] as char);
-            i += 1;
-        }
-        i += 1;
-        let mut left_time_vec = Vec::new();
-        while buffer[i] != '\n' as u8 {
-            left_time_vec.push(buffer[i]);
-            i += 1;
-        }
-        let left_time: i32 = concat(&left_time_vec);
-        return (my_color, opponent_name, left_time);
-    }else{
-        panic!("could not receive 'START'");
+    if args.len() >= 2 && args[1] == "mpc" { // ProbCutのパラメータを求めるモード
+        mpc_main(&args[2..]);
+        return ();
     }
-}
 
-fn main() {
-    let args: Vec<String> = env::args().collect();
+    if args.len() >= 2 && args[1] == "train" { // 学習モード
+        train_main(&args[2..]);
+        return ();
+    }
+
+    if args.len() >= 2 && args[1] == "book" { // 定石を作るモード
+        book_main(&args[2..]);
+        return ();
+    }
+
+    if args.len() >= 2 && args[1] == "analyze" { // 局面の検討モード
+        analyze_main(&args[2..]);
+        return ();
+    }
 
     let mut host = "localhost";
     let mut port = "3000";
     let mut name = "Player";
+    let mut record: Option<String> = None;
+    let mut engine = Engine::new();
 
     let mut i = 1;
     while i < args.len() {
@@ -1117,11 +659,19 @@ fn main() {
         }else if args[i] == "-n" { // name
             name = &args[i+1];
             i += 2;
+        }else if args[i] == "-R" { // 対局ごとに棋譜を書き足すファイル
+            record = Some(args[i+1].clone());
+            i += 2;
+        }else if parse_engine_flag(&mut engine, &args, i) {
+            i += 2;
         }else {
             panic!("INVALID args");
         }
     }
 
+    print_engine(&engine);
+    let engine = Arc::new(engine);
+
     let host_and_port = format!("{}:{}", host, port);
     let mut addrs = host_and_port.to_socket_addrs().unwrap();
 
@@ -1145,16 +695,15 @@ fn main() {
                 let mut left_time = 0;
                 // 盤面の履歴の情報を格納
                 let mut board_info_history = Vec::<BoardInfo>::new();
+                // 打たれた手の履歴（棋譜）の情報を格納
+                let mut move_history = Vec::<u64>::new();
                 // 盤面の情報を格納
-                let mut board_info = BoardInfo{
-                    now_turn: BLACK,
-                    now_index: 1,
-                    player_board: 0x0000000810000000,
-                    opponent_board: 0x0000001008000000,
-                };
+                let mut board_info = BoardInfo::new();
+                // 1局を通して使う探索の状態（置換表など）を格納
+                let mut searcher = Searcher::new(&engine);
 
                 // メインループ
-                let mut bit: u64 = 0; // 打つ手（0ならpassを表す）
+                let mut bit: u64; // 打つ手（0ならpassを表す）
                 let mut way_of_eval: i8; // 評価関数をどれにするかを定める
                 let mut limit: i8;
 
@@ -1175,14 +724,11 @@ fn main() {
                             _opponent_name = tmp.1;
                             left_time = tmp.2;
                             board_info_history = Vec::<BoardInfo>::new();
-                            board_info = BoardInfo{
-                                now_turn: BLACK,
-                                now_index: 1,
-                                player_board: 0x0000000810000000,
-                                opponent_board: 0x0000001008000000,
-                            };
+                            move_history = Vec::<u64>::new();
+                            board_info = BoardInfo::new();
+                            searcher = Searcher::new(&engine);
                             is_waiting = false;
-                            game_start(&board_info);
+                            game_start(&engine, &board_info);
                         }else{
                             panic!("could not receive 'BYE' or 'START'");
                         }
@@ -1191,32 +737,10 @@ fn main() {
                     if board_info.now_turn == my_color { //自分のターン
 
                         // 自分の手を思考
-                        way_of_eval = choose_evaluator(&board_info);
-                        limit = match way_of_eval {
-                            EVAL_BY_POINTTABLE => EVAL_BY_POINTTABLE_DEPTH,
-                            EVAL_NORMAL        => EVAL_NORMAL_DEPTH,
-                            EVAL_WIN           => EVAL_WIN_DEPTH,
-                            EVAL_PERFECT       => EVAL_PERFECT_DEPTH,
-                            _                  => panic!("there is not such way of evaluation"),
-                        };
-
-                        if left_time < 1000 { // 残り時間が1s以下になったら適当に選ぶ
-                            let legal_board: u64 = make_legal_board(&board_info);
-                            if legal_board == 0 as u64 { // おける手がなければパスを選択
-                                bit = 0;
-                            }else{
-                                let mut mask = 0x800000000000000;
-                                for _ in 0..BOARDSIZE {
-                                    if mask & legal_board != 0 {
-                                        bit = mask;
-                                        break;
-                                    }
-                                    mask = mask >> 1;
-                                }
-                            }
-                        }else{
-                            bit = decide(&mut board_info, left_time, way_of_eval, limit);
-                        }
+                        way_of_eval = choose_evaluator(&engine, &board_info);
+                        limit = choose_limit(&engine, way_of_eval);
+
+                        bit = decide(&mut searcher, &mut board_info, left_time, way_of_eval, limit).0;
                     
                         // 自分の手を送信
                         if bit == 0 as u64 {
@@ -1231,7 +755,10 @@ fn main() {
 
                         if buffer[0] == 'E' as u8 { // ENDがきた
                             // 試合終了
-                            game_set(&board_info);
+                            game_set(&engine, &board_info);
+                            if let Some(path) = &record { // 打った手は受け付けられていない
+                                record_game(path, &move_history);
+                            }
                             is_waiting = true;
                             continue;
                         }else if buffer[0] == 'A' as u8 { // ACKがきた
@@ -1244,6 +771,7 @@ fn main() {
                             left_time = concat(&left_time_vec);
 
                             board_info_history.push(board_info.clone());
+                            move_history.push(bit);
 
                             match place(bit, &mut board_info) {
                                 CONTINUE | PLACE_ERR | GAME_SET => {swap(&mut board_info);
@@ -1272,6 +800,7 @@ fn main() {
                             }
                         
                             board_info_history.push(board_info.clone());
+                            move_history.push(bit);
 
                             match place(bit, &mut board_info) {
                                 CONTINUE | PLACE_ERR | GAME_SET => {swap(&mut board_info);
@@ -1283,12 +812,19 @@ fn main() {
 
                         }else if buffer[0] == 'E' as u8 { // ENDがきた
                             // 試合終了
-                            game_set(&board_info);
+                            game_set(&engine, &board_info);
+                            if let Some(path) = &record {
+                                if let Some(last_bit) = find_last_move(&board_info, my_color, &buffer) {
+                                    move_history.push(last_bit);
+                                }
+                                record_game(path, &move_history);
+                            }
                             is_waiting = true;
                             continue;
                         }else if buffer[0] == 'U' as u8 { // UNDOがきた
                             board_info_history.pop();
                             board_info = board_info_history.pop().unwrap();
+                            move_history.truncate(board_info_history.len());
                         }else{
                             panic!("could not receive 'MOVE'");
                         }     
@@ -1299,4 +835,4 @@ fn main() {
     }else{
         panic!("invalid host:port");
     }
-}
+}
\ No newline at end of file
@@ -0,0 +1,155 @@
+// 探索する手の並べ替え（move ordering）
+// 置換表の最善手，キラー手，ヒストリー，速さ優先（相手の着手可能手数が少ない手を先に調べる）を用いる
+
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+
+use crate::board::*;
+use crate::eval::*;
+
+// 並べ替えに用いる得点
+const ORDER_TT_MOVE: i32 = 1 << 29;
+const ORDER_KILLER: i32 = 1 << 28;
+const HISTORY_MAX: u32 = 1 << 20;      // ヒストリーの値の上限（キラー手を超えないようにする）
+const ORDER_MOBILITY_WEIGHT: i32 = 64; // 速さ優先で，相手の着手可能手数1つあたりの減点
+
+// 中盤で速さ優先の並べ替えを行う残り深さの下限（浅いところでは並べ替えのコストの方が大きい）
+const ORDER_MOBILITY_MIN_DEPTH: i8 = 3;
+
+// 残り深さごとに記録するキラー手の深さの上限
+const MAX_DEPTH: usize = 128;
+
+// 並べ替えのために探索中に記録する情報，スレッド間で共有する
+pub struct OrderingTables {
+    killers: Vec<[AtomicU64; 2]>, // 残り深さごとに，βカットを起こした手を2つまで記録する
+    history: Vec<AtomicU32>,      // マスごとに，βカットを起こした回数を深さで重みづけして記録する
+}
+
+impl OrderingTables {
+    pub fn new() -> OrderingTables {
+        let mut killers = Vec::with_capacity(MAX_DEPTH);
+        for _ in 0..MAX_DEPTH {
+            killers.push([AtomicU64::new(0), AtomicU64::new(0)]);
+        }
+        let mut history = Vec::with_capacity(BOARDSIZE as usize);
+        for _ in 0..BOARDSIZE {
+            history.push(AtomicU32::new(0));
+        }
+        return OrderingTables {
+            killers,
+            history,
+        };
+    }
+
+    // 残り深さlimitのノードでplace_bitがβカットを起こしたことを記録する
+    pub fn record_cutoff(&self, place_bit: u64, limit: i8) {
+        let killers = &self.killers[limit as usize % MAX_DEPTH];
+        if killers[0].load(Ordering::Relaxed) != place_bit {
+            killers[1].store(killers[0].load(Ordering::Relaxed), Ordering::Relaxed);
+            killers[0].store(place_bit, Ordering::Relaxed);
+        }
+
+        let history = &self.history[place_bit.trailing_zeros() as usize];
+        let value = history.load(Ordering::Relaxed);
+        history.store(std::cmp::min(value + (limit as u32) * (limit as u32), HISTORY_MAX), Ordering::Relaxed);
+    }
+}
+
+impl Default for OrderingTables {
+    fn default() -> Self {
+        return OrderingTables::new();
+    }
+}
+
+// 相手の着手可能手数
+fn opponent_mobility(board_info: &BoardInfo, place_bit: u64) -> i32 {
+    let mut tmp_board_info: BoardInfo = board_info.clone();
+    place(place_bit, &mut tmp_board_info);
+    swap(&mut tmp_board_info);
+    return make_legal_board(&tmp_board_info).count_ones() as i32;
+}
+
+// legal_boardの手をよさそうな順に並べてmovesに入れる，返り値は手の数
+pub fn order_moves(board_info: &BoardInfo, legal_board: u64, tt_move: u64, limit: i8, way_of_eval: i8, tables: &OrderingTables, moves: &mut [u64; 64]) -> usize {
+    let mut scores = [0i32; 64];
+    let mut count = 0;
+
+    let killers = &tables.killers[limit as usize % MAX_DEPTH];
+    let killer0 = killers[0].load(Ordering::Relaxed);
+    let killer1 = killers[1].load(Ordering::Relaxed);
+    // 終盤の読み切りでは速さ優先のみ，中盤ではヒストリーを優先する
+    let endgame = way_of_eval == EVAL_WIN || way_of_eval == EVAL_PERFECT;
+    let use_mobility = endgame || limit >= ORDER_MOBILITY_MIN_DEPTH;
+
+    let mut remaining: u64 = legal_board;
+    while remaining != 0 {
+        let mask: u64 = remaining & remaining.wrapping_neg();
+        remaining ^= mask;
+
+        let score: i32 = if mask == tt_move {
+            ORDER_TT_MOVE
+        }else if mask == killer0 {
+            ORDER_KILLER
+        }else if mask == killer1 {
+            ORDER_KILLER - 1
+        }else{
+            let mut tmp: i32 = 0;
+            if !endgame {
+                tmp += tables.history[mask.trailing_zeros() as usize].load(Ordering::Relaxed) as i32 * ORDER_MOBILITY_WEIGHT;
+            }
+            if use_mobility {
+                tmp -= opponent_mobility(board_info, mask) * ORDER_MOBILITY_WEIGHT;
+            }
+            tmp
+        };
+
+        // 挿入ソート（得点の高い順）
+        let mut j = count;
+        while j > 0 && scores[j-1] < score {
+            scores[j] = scores[j-1];
+            moves[j] = moves[j-1];
+            j -= 1;
+        }
+        scores[j] = score;
+        moves[j] = mask;
+        count += 1;
+    }
+
+    return count;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::random::{random_game, Rng};
+
+    #[test]
+    fn order_moves_keeps_every_legal_move() {
+        let tables = OrderingTables::new();
+        let mut rng = Rng::new(9);
+        for board_info in random_game(&mut rng).0.iter() {
+            let legal_board = make_legal_board(board_info);
+            for &way_of_eval in [EVAL_NORMAL, EVAL_PERFECT].iter() {
+                let mut moves = [0u64; 64];
+                let count = order_moves(board_info, legal_board, 0, 5, way_of_eval, &tables, &mut moves);
+                assert_eq!(count, legal_board.count_ones() as usize);
+                assert_eq!(moves[..count].iter().fold(0, |acc, &mask| acc | mask), legal_board);
+            }
+        }
+    }
+
+    #[test]
+    fn tt_move_comes_before_killers() {
+        let tables = OrderingTables::new();
+        let board_info = BoardInfo::new();
+        let legal_board = make_legal_board(&board_info);
+        let squares: Vec<u64> = ["D3", "C4", "F5", "E6"].iter().map(|s| string_to_move(s).unwrap()).collect();
+        assert_eq!(squares.iter().fold(0, |acc, &mask| acc | mask), legal_board);
+        tables.record_cutoff(squares[1], 6);
+        tables.record_cutoff(squares[2], 6); // squares[2]が1番目，squares[1]が2番目のキラー手になる
+        let mut moves = [0u64; 64];
+        let count = order_moves(&board_info, legal_board, squares[3], 6, EVAL_NORMAL, &tables, &mut moves);
+        assert_eq!(&moves[..3], &[squares[3], squares[2], squares[1]]);
+        assert_eq!(moves[3], squares[0]);
+        assert_eq!(count, 4);
+    }
+}
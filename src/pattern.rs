@@ -0,0 +1,219 @@
+// パターンによる評価関数
+// 辺+2X，隅の3x3・2x5，各段，斜めの並びの石の配置（空き・自分・相手の3通り）ごとに重みをもち，その合計を評価値とする
+// 重みは空きマス数による段階（phase）ごとに別のものを用い，ファイルから読み込む
+
+use std::fs;
+use std::sync::OnceLock;
+
+use crate::board::*;
+use crate::eval::weights_fingerprint;
+use crate::symmetry::*;
+
+// 重みの単位（1石差を何点とするか）
+pub const PATTERN_SCALE: f32 = 100.0;
+// 段階の数と，1段階あたりの空きマス数
+pub const PATTERN_PHASES: usize = 6;
+pub const PATTERN_PHASE_SIZE: i32 = 10;
+
+// パターンの名前と，基準となる向きのマス（(列, 行)，0始まり）
+const PATTERNS: [(&str, &[(i32, i32)]); 13] = [
+    ("edge2x",    &[(0,0), (1,0), (2,0), (3,0), (4,0), (5,0), (6,0), (7,0), (1,1), (6,1)]),
+    ("corner3x3", &[(0,0), (1,0), (2,0), (0,1), (1,1), (2,1), (0,2), (1,2), (2,2)]),
+    ("corner2x5", &[(0,0), (1,0), (2,0), (3,0), (4,0), (0,1), (1,1), (2,1), (3,1), (4,1)]),
+    ("line2",     &[(0,1), (1,1), (2,1), (3,1), (4,1), (5,1), (6,1), (7,1)]),
+    ("line3",     &[(0,2), (1,2), (2,2), (3,2), (4,2), (5,2), (6,2), (7,2)]),
+    ("line4",     &[(0,3), (1,3), (2,3), (3,3), (4,3), (5,3), (6,3), (7,3)]),
+    ("diag8",     &[(0,0), (1,1), (2,2), (3,3), (4,4), (5,5), (6,6), (7,7)]),
+    ("diag7",     &[(1,0), (2,1), (3,2), (4,3), (5,4), (6,5), (7,6)]),
+    ("diag6",     &[(2,0), (3,1), (4,2), (5,3), (6,4), (7,5)]),
+    ("diag5",     &[(3,0), (4,1), (5,2), (6,3), (7,4)]),
+    ("diag4",     &[(4,0), (5,1), (6,2), (7,3)]),
+    ("diag3",     &[(5,0), (6,1), (7,2)]),
+    ("corner2x2", &[(0,0), (1,0), (0,1), (1,1)]),
+];
+
+// 盤面上の1つのパターン（対称な向きのそれぞれが1つずつある）
+struct PatternInstance {
+    pattern: usize,  // PATTERNSのインデックス
+    cells: Vec<u64>, // マスのビット（PATTERNSのマスと同じ順）
+}
+
+impl PatternInstance {
+    // board_infoでのこのパターンの配置の番号（マスごとに空き0・自分1・相手2とした3進数）
+    fn index(&self, board_info: &BoardInfo) -> usize {
+        let mut index = 0;
+        for &bit in self.cells.iter() {
+            index *= 3;
+            if board_info.player_board & bit != 0 {
+                index += 1;
+            }else if board_info.opponent_board & bit != 0 {
+                index += 2;
+            }
+        }
+        return index;
+    }
+}
+
+// 全パターンの情報
+struct PatternSet {
+    instances: Vec<PatternInstance>,
+    offsets: Vec<usize>, // 1段階の重みの中での各パターンの先頭の位置
+    phase_size: usize,   // 1段階あたりの重みの数
+}
+
+fn pow3(n: usize) -> usize {
+    return 3usize.pow(n as u32);
+}
+
+// (列, 行)をビットにする（A1が最上位ビット）
+fn cell_to_bit(x: i32, y: i32) -> u64 {
+    return 1u64 << (63 - (y * LINESIZE + x));
+}
+
+fn make_pattern_set() -> PatternSet {
+    let mut instances = Vec::<PatternInstance>::new();
+    let mut offsets = Vec::<usize>::new();
+    let mut phase_size = 0;
+    for (pattern, (_, cells)) in PATTERNS.iter().enumerate() {
+        offsets.push(phase_size);
+        phase_size += pow3(cells.len());
+        // 同じマスの集まりになる向きは1つだけ残す
+        let mut seen = Vec::<u64>::new();
+        for k in 0..SYMMETRIES {
+            let bits: Vec<u64> = cells.iter().map(|&(x, y)| transform(k, cell_to_bit(x, y))).collect();
+            let mask = bits.iter().fold(0, |acc, bit| acc | bit);
+            if seen.contains(&mask) {
+                continue;
+            }
+            seen.push(mask);
+            instances.push(PatternInstance {
+                pattern,
+                cells: bits,
+            });
+        }
+    }
+    return PatternSet {
+        instances,
+        offsets,
+        phase_size,
+    };
+}
+
+fn pattern_set() -> &'static PatternSet {
+    static PATTERN_SET: OnceLock<PatternSet> = OnceLock::new();
+    return PATTERN_SET.get_or_init(make_pattern_set);
+}
+
+// 空きマス数に対応する段階
+pub fn phase_of(board_info: &BoardInfo) -> usize {
+    let empties = BOARDSIZE - (board_info.player_board | board_info.opponent_board).count_ones() as i32;
+    return std::cmp::min(std::cmp::max(empties - 1, 0) / PATTERN_PHASE_SIZE, PATTERN_PHASES as i32 - 1) as usize;
+}
+
+// 1段階あたりの重みの数
+pub fn phase_size() -> usize {
+    return pattern_set().phase_size;
+}
+
+// board_infoに現れるパターンの配置の，1段階の重みの中での位置をfeaturesに入れる（手番側からみる）
+pub fn pattern_features(board_info: &BoardInfo, features: &mut Vec<usize>) {
+    let set = pattern_set();
+    features.clear();
+    for instance in set.instances.iter() {
+        features.push(set.offsets[instance.pattern] + instance.index(board_info));
+    }
+}
+
+// パターンの重み（段階ごと，単位は石数）
+pub struct PatternWeights {
+    weights: Vec<f32>, // [段階][1段階の中での位置]
+}
+
+impl PatternWeights {
+    // 重みがすべて0のものを作る
+    pub fn new() -> PatternWeights {
+        return PatternWeights {
+            weights: vec![0.0; PATTERN_PHASES * phase_size()],
+        };
+    }
+
+    pub fn get(&self, phase: usize, feature: usize) -> f32 {
+        return self.weights[phase * phase_size() + feature];
+    }
+
+    pub fn set(&mut self, phase: usize, feature: usize, weight: f32) {
+        let size = phase_size();
+        self.weights[phase * size + feature] = weight;
+    }
+
+    // 重みの指紋
+    pub fn fingerprint(&self) -> u64 {
+        return weights_fingerprint(self.weights.iter().map(|weight| weight.to_bits()));
+    }
+
+    // テキストから重みを読み込む
+    // 書式は1行に「段階 パターン名 配置の番号 重み」，書かれていない重みは0，#以降はコメント
+    pub fn parse(text: &str) -> Result<PatternWeights, String> {
+        let set = pattern_set();
+        let mut weights = PatternWeights::new();
+        for (i, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap().trim();
+            if line.is_empty() {
+                continue;
+            }
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if fields.len() != 4 {
+                return Err(format!("line {}: expected 4 fields", i + 1));
+            }
+            let phase: usize = fields[0].parse().map_err(|_| format!("line {}: invalid phase", i + 1))?;
+            let pattern = PATTERNS.iter().position(|p| p.0 == fields[1]).ok_or(format!("line {}: unknown pattern {}", i + 1, fields[1]))?;
+            let index: usize = fields[2].parse().map_err(|_| format!("line {}: invalid index", i + 1))?;
+            let weight: f32 = fields[3].parse().map_err(|_| format!("line {}: invalid weight", i + 1))?;
+            if phase >= PATTERN_PHASES || index >= pow3(PATTERNS[pattern].1.len()) {
+                return Err(format!("line {}: phase or index out of range", i + 1));
+            }
+            weights.set(phase, set.offsets[pattern] + index, weight);
+        }
+        return Ok(weights);
+    }
+
+    // ファイルから重みを読み込む
+    pub fn load(path: &str) -> Result<PatternWeights, String> {
+        let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+        return PatternWeights::parse(&text).map_err(|e| format!("{}: {}", path, e));
+    }
+
+    // parse()で読み込める形式のテキストにする（0の重みは書かない）
+    pub fn to_text(&self) -> String {
+        let set = pattern_set();
+        let mut text = String::from("# phase pattern index weight\n");
+        for phase in 0..PATTERN_PHASES {
+            for (pattern, (name, cells)) in PATTERNS.iter().enumerate() {
+                for index in 0..pow3(cells.len()) {
+                    let weight = self.get(phase, set.offsets[pattern] + index);
+                    if weight != 0.0 {
+                        text += &format!("{} {} {} {}\n", phase, name, index, weight);
+                    }
+                }
+            }
+        }
+        return text;
+    }
+}
+
+impl Default for PatternWeights {
+    fn default() -> Self {
+        return PatternWeights::new();
+    }
+}
+
+// パターンの重みweightsによる評価値（手番側からみた予想石差 * PATTERN_SCALE）
+pub fn eval_pattern(board_info: &BoardInfo, weights: &PatternWeights) -> i32 {
+    let set = pattern_set();
+    let phase = phase_of(board_info);
+    let mut sum: f32 = 0.0;
+    for instance in set.instances.iter() {
+        sum += weights.get(phase, set.offsets[instance.pattern] + instance.index(board_info));
+    }
+    return (sum * PATTERN_SCALE).round() as i32;
+}
@@ -0,0 +1,72 @@
+// 決まった数のワーカースレッドで仕事をこなすスレッドプール
+// 仕事はキューに積まれ，空いているワーカーから順に取り出して実行する
+
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+
+type Job = Box<dyn FnOnce() + Send + 'static>;
+
+pub struct ThreadPool {
+    workers: Vec<thread::JoinHandle<()>>,
+    sender: Option<mpsc::Sender<Job>>,
+}
+
+impl ThreadPool {
+    // size個のワーカーをもつスレッドプールを作る
+    pub fn new(size: usize) -> ThreadPool {
+        assert!(size > 0, "thread pool needs at least one worker");
+
+        let (sender, receiver) = mpsc::channel::<Job>();
+        let receiver = Arc::new(Mutex::new(receiver));
+        let mut workers = Vec::with_capacity(size);
+        for _ in 0..size {
+            let receiver = Arc::clone(&receiver);
+            workers.push(thread::spawn(move || {
+                loop {
+                    // ロックは仕事を取り出す間だけ持つ
+                    let job = receiver.lock().unwrap().recv();
+                    match job {
+                        Ok(job) => job(),
+                        Err(_) => break, // プールが破棄された
+                    };
+                }
+            }));
+        }
+
+        return ThreadPool {
+            workers,
+            sender: Some(sender),
+        };
+    }
+
+    // このマシンで同時に動かせるスレッド数
+    pub fn default_size() -> usize {
+        return match thread::available_parallelism() {
+            Ok(n) => n.get(),
+            Err(_) => 1,
+        };
+    }
+
+    pub fn size(&self) -> usize {
+        return self.workers.len();
+    }
+
+    // 仕事をキューに積む
+    pub fn execute<F>(&self, f: F) where F: FnOnce() + Send + 'static {
+        self.sender.as_ref().unwrap().send(Box::new(f)).expect("thread pool has stopped");
+    }
+}
+
+impl Drop for ThreadPool {
+    // キューを閉じて，全ワーカーが残りの仕事を終えるのを待つ
+    // ワーカーの仕事の中でプールが破棄されたときは，そのワーカー自身は待たない（仕事を終えればキューが閉じているので抜ける）
+    fn drop(&mut self) {
+        drop(self.sender.take());
+        let current = thread::current().id();
+        for worker in self.workers.drain(..) {
+            if worker.thread().id() != current {
+                worker.join().unwrap();
+            }
+        }
+    }
+}
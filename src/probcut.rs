@@ -0,0 +1,355 @@
+// 中盤の選択的探索（Multi-ProbCut）のパラメータと，それを自己対戦の局面から求める処理
+// 深さdの探索の評価値vを，深さd'(<d)の浅い探索の評価値v'から v = a*v' + b + e（eは標準偏差sigmaの誤差）と予測し，
+// vが探索窓の外に出ることがほぼ確実なら深い探索を省略する．a, b, sigmaは空きマス数（ステージ）と深さの組ごとに線形回帰で求める
+// パラメータは求めたときの評価関数と重みの指紋をもち，探索ではそれが今の評価関数と重みに一致するときだけ用いる
+
+use std::fs;
+use std::sync::Arc;
+
+use crate::board::*;
+use crate::engine::Engine;
+use crate::eval::*;
+use crate::search::*;
+use crate::random::Rng;
+
+// ProbCutを行う残り深さの範囲
+pub const MPC_MIN_DEPTH: i8 = 3;
+pub const MPC_MAX_DEPTH: i8 = 30;
+// 空きマス何個ごとにパラメータを分けるか
+pub const MPC_STAGE_SIZE: i32 = 10;
+const MPC_STAGES: usize = (BOARDSIZE / MPC_STAGE_SIZE) as usize + 1;
+// 予測値が窓からsigmaの何倍離れていたらカットするか
+pub const MPC_THRESHOLD: f64 = 1.5;
+// これより絶対値の大きい窓ではカットを試さない（探索窓が全幅のときなど）
+pub const MPC_SCORE_LIMIT: i32 = 1 << 24;
+// 回帰に用いる標本数の下限
+const MPC_MIN_SAMPLES: usize = 10;
+
+// 組み込みのパラメータ（EVAL_NORMALの既定の重みについて`reversi mpc -g 24 -d 10`で求めたもの，深さ11以上はextrapolate()で補う）
+// 書式は評価関数の名前の行「evaluator normal」と重みの指紋の行「weights 16進数」のあとに，1行に「ステージ 深さ 浅い探索の深さ a b sigma」，#以降はコメント
+const DEFAULT_MPC_PARAMS: &str = "
+evaluator normal
+weights 71da5082db069160
+2 3 1 0.9942 36.08 257.20
+2 4 0 1.0016 48.88 376.95
+2 4 2 1.0016 25.40 235.34
+2 5 1 0.9987 63.80 345.50
+2 6 0 1.0200 58.64 455.07
+2 6 2 1.0231 34.26 326.56
+2 7 1 1.0217 77.23 419.41
+2 7 3 1.0324 39.69 298.35
+2 8 0 1.0520 65.91 531.59
+2 8 4 1.0596 12.91 303.73
+2 9 1 1.0465 96.80 501.33
+2 9 3 1.0610 58.00 384.26
+2 10 0 1.0746 72.98 599.55
+2 10 4 1.0872 17.97 377.41
+3 3 1 0.9576 49.38 229.76
+3 4 0 0.9169 -9.47 368.85
+3 4 2 0.9762 14.66 237.77
+3 5 1 0.9334 64.01 335.87
+3 6 0 0.8761 26.11 413.25
+3 6 2 0.9341 49.02 309.77
+3 7 1 0.8999 56.57 385.21
+3 7 3 0.9561 8.11 275.00
+3 8 0 0.8639 38.38 465.03
+3 8 4 0.9621 44.97 250.20
+3 9 1 0.8960 64.08 441.86
+3 9 3 0.9596 14.85 332.63
+3 10 0 0.8694 59.33 510.43
+3 10 4 0.9728 65.42 311.83
+4 3 1 0.9370 69.06 206.22
+4 4 0 0.8812 -50.00 283.71
+4 4 2 0.9632 3.64 186.51
+4 5 1 0.9060 77.90 244.21
+4 6 0 0.8426 -25.95 313.78
+4 6 2 0.9191 25.37 241.06
+4 7 1 0.8816 68.92 287.00
+4 7 3 0.9387 4.35 215.64
+4 8 0 0.8416 1.27 346.45
+4 8 4 0.9661 48.82 193.71
+4 9 1 0.8827 59.95 309.01
+4 9 3 0.9397 -4.66 244.36
+4 10 0 0.8303 29.09 365.09
+4 10 4 0.9597 75.88 218.01
+5 3 1 0.7645 74.10 174.23
+5 4 0 0.6262 -115.95 193.44
+5 4 2 0.7323 -15.09 157.00
+5 5 1 0.7182 66.46 168.25
+5 6 0 0.5407 -93.54 220.53
+5 6 2 0.6938 -0.45 173.67
+5 7 1 0.6472 50.74 192.06
+5 7 3 0.7978 -1.91 150.66
+5 8 0 0.4958 -88.82 221.50
+5 8 4 0.8164 5.12 149.30
+5 9 1 0.6061 43.97 178.92
+5 9 3 0.7345 -2.72 146.08
+5 10 0 0.4791 -75.65 223.53
+5 10 4 0.7890 15.13 158.00
+";
+
+// 1つのカットのパラメータ
+#[derive(Clone)]
+pub struct MpcCut {
+    pub shallow: i8, // 浅い探索の深さ
+    pub a: f64,
+    pub b: f64,
+    pub sigma: f64,
+}
+
+// 全ステージ・深さのパラメータ
+pub struct MpcParams {
+    pub evaluator: i8,            // パラメータを求めた評価関数（EVAL_NORMALかEVAL_PATTERN）
+    pub weights_fingerprint: u64, // パラメータを求めたときの評価関数の重みの指紋
+    cuts: Vec<Vec<Vec<MpcCut>>>,  // [ステージ][深さ] -> 浅い探索の深さが小さい順のカット
+}
+
+// パラメータのファイルでの評価関数の名前
+fn evaluator_name(evaluator: i8) -> &'static str {
+    return if evaluator == EVAL_PATTERN {"pattern"} else {"normal"};
+}
+
+impl MpcParams {
+    // 評価関数evaluatorの，指紋がweights_fingerprintの重みについての，カットを1つももたないパラメータを作る
+    pub fn new(evaluator: i8, weights_fingerprint: u64) -> MpcParams {
+        return MpcParams {
+            evaluator,
+            weights_fingerprint,
+            cuts: vec![vec![Vec::new(); MPC_MAX_DEPTH as usize + 1]; MPC_STAGES],
+        };
+    }
+
+    // ステージstage，深さdepthのカットを追加する
+    pub fn add(&mut self, stage: usize, depth: i8, cut: MpcCut) {
+        let cuts = &mut self.cuts[stage][depth as usize];
+        let index = cuts.iter().position(|c| c.shallow > cut.shallow).unwrap_or(cuts.len());
+        cuts.insert(index, cut);
+    }
+
+    // 空きマス数empties，残り深さdepthのノードで試すカット
+    pub fn cuts(&self, empties: i32, depth: i8) -> &[MpcCut] {
+        if !(MPC_MIN_DEPTH..=MPC_MAX_DEPTH).contains(&depth) {
+            return &[];
+        }
+        return &self.cuts[stage_of(empties)][depth as usize];
+    }
+
+    // 求められていない深さのカットを，同じステージで偶奇の同じ一番近い浅い深さのパラメータで補う
+    // 浅い探索の深さはshallow_depths()の最も深いものにする（深さの比をおおよそ保つ）
+    fn extrapolate(&mut self) {
+        for stage_cuts in self.cuts.iter_mut() {
+            for depth in (MPC_MIN_DEPTH + 2)..=MPC_MAX_DEPTH {
+                if !stage_cuts[depth as usize].is_empty() {
+                    continue;
+                }
+                let base = match stage_cuts[depth as usize - 2].last() {
+                    Some(cut) => cut.clone(),
+                    None => continue,
+                };
+                let shallow = *shallow_depths(depth).last().unwrap();
+                stage_cuts[depth as usize].push(MpcCut {
+                    shallow,
+                    ..base
+                });
+            }
+        }
+    }
+
+    // 組み込みのパラメータ
+    pub fn built_in() -> MpcParams {
+        return MpcParams::parse(DEFAULT_MPC_PARAMS).expect("invalid built-in ProbCut parameters");
+    }
+
+    // テキストからパラメータを読み込む
+    pub fn parse(text: &str) -> Result<MpcParams, String> {
+        let mut evaluator: Option<i8> = None;
+        let mut weights_fingerprint: Option<u64> = None;
+        let mut params = MpcParams::new(EVAL_NORMAL, 0);
+        for (i, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap().trim();
+            if line.is_empty() {
+                continue;
+            }
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if fields.len() == 2 && fields[0] == "evaluator" {
+                evaluator = Some(match fields[1] {
+                    "normal" => EVAL_NORMAL,
+                    "pattern" => EVAL_PATTERN,
+                    _ => return Err(format!("line {}: unknown evaluator {}", i + 1, fields[1])),
+                });
+                continue;
+            }
+            if fields.len() == 2 && fields[0] == "weights" {
+                weights_fingerprint = Some(u64::from_str_radix(fields[1], 16).map_err(|_| format!("line {}: invalid weights fingerprint", i + 1))?);
+                continue;
+            }
+            if fields.len() != 6 {
+                return Err(format!("line {}: expected 6 fields", i + 1));
+            }
+            let stage: usize = fields[0].parse().map_err(|_| format!("line {}: invalid stage", i + 1))?;
+            let depth: i8 = fields[1].parse().map_err(|_| format!("line {}: invalid depth", i + 1))?;
+            let shallow: i8 = fields[2].parse().map_err(|_| format!("line {}: invalid shallow depth", i + 1))?;
+            let a: f64 = fields[3].parse().map_err(|_| format!("line {}: invalid a", i + 1))?;
+            let b: f64 = fields[4].parse().map_err(|_| format!("line {}: invalid b", i + 1))?;
+            let sigma: f64 = fields[5].parse().map_err(|_| format!("line {}: invalid sigma", i + 1))?;
+            if stage >= MPC_STAGES || !(MPC_MIN_DEPTH..=MPC_MAX_DEPTH).contains(&depth) || shallow < 0 || shallow >= depth {
+                return Err(format!("line {}: stage or depth out of range", i + 1));
+            }
+            if a <= 0.0 || sigma < 0.0 {
+                return Err(format!("line {}: a must be positive and sigma non-negative", i + 1));
+            }
+            params.add(stage, depth, MpcCut {
+                shallow,
+                a,
+                b,
+                sigma,
+            });
+        }
+        params.evaluator = evaluator.ok_or("missing evaluator line (the evaluator the parameters were fitted for)")?;
+        params.weights_fingerprint = weights_fingerprint.ok_or("missing weights line (the fingerprint of the weights the parameters were fitted for)")?;
+        params.extrapolate();
+        return Ok(params);
+    }
+
+    // ファイルからパラメータを読み込む
+    pub fn load(path: &str) -> Result<MpcParams, String> {
+        let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+        return MpcParams::parse(&text).map_err(|e| format!("{}: {}", path, e));
+    }
+
+    // parse()で読み込める形式のテキストにする
+    pub fn to_text(&self) -> String {
+        let mut text = format!("evaluator {}\nweights {:016x}\n# stage depth shallow a b sigma\n", evaluator_name(self.evaluator), self.weights_fingerprint);
+        for (stage, stage_cuts) in self.cuts.iter().enumerate() {
+            for (depth, cuts) in stage_cuts.iter().enumerate() {
+                for cut in cuts.iter() {
+                    text += &format!("{} {} {} {:.4} {:.2} {:.2}\n", stage, depth, cut.shallow, cut.a, cut.b, cut.sigma);
+                }
+            }
+        }
+        return text;
+    }
+}
+
+// 空きマス数に対応するステージ
+fn stage_of(empties: i32) -> usize {
+    return std::cmp::min(empties / MPC_STAGE_SIZE, MPC_STAGES as i32 - 1) as usize;
+}
+
+// 深さdepthの探索を予測するのに用いる浅い探索の深さ（小さい順）
+// 評価値は手番によって偏るので，深さとの差が偶数になるようにする
+pub fn shallow_depths(depth: i8) -> Vec<i8> {
+    let mut half = depth / 2;
+    if (depth - half) % 2 != 0 {
+        half -= 1;
+    }
+    let mut depths = vec![depth % 2];
+    if half > depth % 2 {
+        depths.push(half);
+    }
+    return depths;
+}
+
+// 浅い探索にときどきランダムな手を混ぜた自己対戦をgames局行い，中盤の局面を集める
+pub fn collect_positions(engine: &Arc<Engine>, games: usize, seed: u64) -> Vec<BoardInfo> {
+    let mut rng = Rng::new(seed);
+    let shared = SearchShared::new(engine, 1);
+    let mut positions = Vec::<BoardInfo>::new();
+
+    for _ in 0..games {
+        let mut board_info = BoardInfo::new();
+        while !is_game_over(&board_info) {
+            let legal_board = make_legal_board(&board_info);
+            if legal_board == 0 {
+                swap(&mut board_info);
+                continue;
+            }
+            let empties = 64 - (board_info.player_board | board_info.opponent_board).count_ones() as i32;
+            if empties > engine.config.depth(EVAL_WIN) as i32 + 1 {
+                positions.push(board_info.clone());
+            }
+
+            let mut place_bit: u64 = 0;
+            if rng.next_u64() & 3 == 0 { // 4回に1回はランダムに打つ
+                place_bit = rng.choose_bit(legal_board);
+            }else{ // 2手読みで最もよい手を打つ
+                let mut best = std::i32::MIN;
+                let mut remaining = legal_board;
+                while remaining != 0 {
+                    let mask = remaining & remaining.wrapping_neg();
+                    remaining ^= mask;
+                    let mut tmp_board_info = board_info.clone();
+                    place(mask, &mut tmp_board_info);
+                    swap(&mut tmp_board_info);
+                    let score = -shared.search(std::i32::MIN+1, std::i32::MAX, 1, &tmp_board_info, EVAL_NORMAL).0;
+                    if score > best {
+                        best = score;
+                        place_bit = mask;
+                    }
+                }
+            }
+            place(place_bit, &mut board_info);
+            swap(&mut board_info);
+        }
+    }
+
+    return positions;
+}
+
+// positionsの各局面をengineの中盤の評価関数で深さ0からmax_depthまでProbCutなしで探索し，浅い探索と深い探索の評価値の関係を線形回帰で求める
+// 求めたパラメータは，その評価関数とengineの今の重みについてのものとなる
+pub fn fit_mpc_params(engine: &Arc<Engine>, positions: &[BoardInfo], max_depth: i8) -> MpcParams {
+    let max_depth = std::cmp::min(max_depth, MPC_MAX_DEPTH);
+    // samples[ステージ][深さ] = 局面ごとの評価値の列（深さ0からmax_depthまで）
+    let mut samples: Vec<Vec<Vec<i32>>> = vec![Vec::new(); MPC_STAGES];
+    let evaluator = engine.midgame_evaluator();
+    let shared = SearchShared::with_probcut(engine, 1, false);
+
+    for (i, board_info) in positions.iter().enumerate() {
+        shared.tt.clear();
+        let mut scores = Vec::<i32>::new();
+        for depth in 0..=max_depth {
+            scores.push(shared.search(std::i32::MIN+1, std::i32::MAX, depth, board_info, evaluator).0);
+        }
+        let empties = 64 - (board_info.player_board | board_info.opponent_board).count_ones() as i32;
+        samples[stage_of(empties)].push(scores);
+        println!("debug: {}/{} positions searched", i + 1, positions.len());
+    }
+
+    let mut params = MpcParams::new(evaluator, engine.weights_fingerprint(evaluator));
+    for (stage, stage_samples) in samples.iter().enumerate() {
+        if stage_samples.len() < MPC_MIN_SAMPLES {
+            continue;
+        }
+        for depth in MPC_MIN_DEPTH..=max_depth {
+            for shallow in shallow_depths(depth) {
+                let pairs: Vec<(f64, f64)> = stage_samples.iter().map(|scores| (scores[shallow as usize] as f64, scores[depth as usize] as f64)).collect();
+                if let Some(cut) = fit_line(&pairs, shallow) {
+                    params.add(stage, depth, cut);
+                }
+            }
+        }
+    }
+    return params;
+}
+
+// y = a*x + bを最小二乗法で求め，残差の標準偏差をsigmaとする
+fn fit_line(pairs: &[(f64, f64)], shallow: i8) -> Option<MpcCut> {
+    let n = pairs.len() as f64;
+    let mean_x = pairs.iter().map(|p| p.0).sum::<f64>() / n;
+    let mean_y = pairs.iter().map(|p| p.1).sum::<f64>() / n;
+    let var_x = pairs.iter().map(|p| (p.0 - mean_x) * (p.0 - mean_x)).sum::<f64>();
+    let cov = pairs.iter().map(|p| (p.0 - mean_x) * (p.1 - mean_y)).sum::<f64>();
+    if var_x == 0.0 || cov <= 0.0 {
+        return None;
+    }
+    let a = cov / var_x;
+    let b = mean_y - a * mean_x;
+    let sigma = (pairs.iter().map(|p| (p.1 - a * p.0 - b) * (p.1 - a * p.0 - b)).sum::<f64>() / n).sqrt();
+    return Some(MpcCut {
+        shallow,
+        a,
+        b,
+        sigma,
+    });
+}
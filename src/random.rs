@@ -0,0 +1,66 @@
+// 乱数と，それを用いたランダムな序盤
+// 定石の手の選択，自己対戦の始まり，ProbCutのパラメータを求める局面集めなどで共通に用いる
+
+use crate::board::*;
+
+// 簡単な乱数（xorshift64），局面に多様性をもたせるために用いる
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Rng {
+        return Rng(seed | 1); // 0だと同じ値しか出ない
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        return self.0;
+    }
+
+    // boardに立っているビットのうち1つをランダムに選ぶ（boardは0でないこと）
+    pub fn choose_bit(&mut self, board: u64) -> u64 {
+        let mut n = self.next_u64() % board.count_ones() as u64;
+        let mut remaining = board;
+        loop {
+            let mask = remaining & remaining.wrapping_neg();
+            if n == 0 {
+                return mask;
+            }
+            remaining ^= mask;
+            n -= 1;
+        }
+    }
+}
+
+// 初期局面からplies手をランダムに打つ（対局の始まりに変化をつけるため），返り値は(その局面，打った手)
+pub fn random_opening(plies: usize, rng: &mut Rng) -> (BoardInfo, Vec<u64>) {
+    let mut board_info = BoardInfo::new();
+    let mut moves = Vec::<u64>::new();
+    for _ in 0..plies {
+        if is_game_over(&board_info) {
+            break;
+        }
+        let legal_board = make_legal_board(&board_info);
+        let place_bit = if legal_board == 0 {0} else {rng.choose_bit(legal_board)};
+        place(place_bit, &mut board_info);
+        swap(&mut board_info);
+        moves.push(place_bit);
+    }
+    return (board_info, moves);
+}
+
+// 終局までランダムに打った対局（テスト用），返り値は(各局面，打った手)
+// positions[i]はmoves[i]を打つ前の局面で，最後の要素は終局の局面（パスは0として手に含まれる）
+#[cfg(test)]
+pub(crate) fn random_game(rng: &mut Rng) -> (Vec<BoardInfo>, Vec<u64>) {
+    let (_, moves) = random_opening(usize::MAX, rng); // 終局まで打つ
+    let mut board_info = BoardInfo::new();
+    let mut positions = vec![board_info.clone()];
+    for &place_bit in moves.iter() {
+        place(place_bit, &mut board_info);
+        swap(&mut board_info);
+        positions.push(board_info.clone());
+    }
+    return (positions, moves);
+}
@@ -0,0 +1,863 @@
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use std::sync::{mpsc, Arc, Condvar, Mutex, Weak};
+use std::sync::atomic::{AtomicBool, AtomicI32, AtomicUsize, Ordering};
+use std::thread;
+
+use crate::board::*;
+use crate::endgame::*;
+use crate::engine::Engine;
+use crate::eval::*;
+use crate::ordering::*;
+use crate::pool::ThreadPool;
+use crate::probcut::*;
+use crate::random::Rng;
+use crate::tt::*;
+
+// 持ち時間のうち，通信などのために常に残しておく時間(ms)
+const TIME_MARGIN: i32 = 1000;
+// 中盤に1手あたりの時間を決めるとき，終盤の読み切りのために何手分の時間を残しておくか
+// 読み切りでは残り時間のこの数分の1までを使う（読み切れなければ中盤の探索の結果を打つので，失う時間はこの分だけ）
+const ENDGAME_TIME_SHARE: i32 = 4;
+// 読み切りの前に中盤の評価関数で探索するとき，1手の時間のうち何分の1を使うか
+const NORMAL_SEARCH_SHARE: i32 = 10;
+// 反復深化で，深さを1つ増やすと探索時間が何倍になると見積もるか
+const BRANCHING_FACTOR: u32 = 4;
+
+// 兄弟ノードを複数のスレッドで分担して探索する残り深さの下限
+const SPLIT_MIN_DEPTH: i8 = 4;
+
+// 反復深化で，1つ前の深さの評価値のまわりに取る探索窓（aspiration window）の幅
+const ASPIRATION_WINDOW: i32 = 64;
+// 探索窓の外に評価値が出たとき，窓の幅を何倍に広げて再探索するか
+const ASPIRATION_WIDEN: i32 = 4;
+
+fn max(a: i32, b: i32) -> i32 {
+    return if a < b {b} else {a};
+}
+
+// 探索するスレッドの間で共有する情報
+// 探索はすべてpoolのワーカーで行う（ルートの手も，兄弟ノードを分担する手伝いも），探索するスレッドは常にmax_threads個まで
+pub struct SearchShared {
+    pub engine: Arc<Engine>,    // 評価関数の重みや設定などを与えるエンジン
+    pub tt: TranspositionTable, // 置換表，評価関数ごとに別のものを用いること（評価値の意味が異なるため）
+    pub abort: AtomicBool,      // trueになったら，各スレッドは探索を打ち切ってすぐに戻る
+    pub ordering: OrderingTables, // 手の並べ替えに用いるキラー手・ヒストリー
+    pub probcut: bool,          // 中盤の探索でMulti-ProbCutを行うならtrue
+    pool: ThreadPool,           // 探索の仕事をこなすmax_threads個のワーカー
+    max_threads: usize,         // 同時に探索してよいスレッド数（poolのワーカー数）
+    busy_threads: AtomicUsize,  // poolに積まれてまだ終わっていない仕事の数
+    this: Weak<SearchShared>,   // 自分自身（poolに積む仕事に持たせる）
+}
+
+impl SearchShared {
+    // engineで，max_threads個のスレッドで探索するSearchSharedを作る
+    pub fn new(engine: &Arc<Engine>, max_threads: usize) -> Arc<SearchShared> {
+        return SearchShared::with_probcut(engine, max_threads, true);
+    }
+
+    // probcutがfalseならMulti-ProbCutを行わないSearchSharedを作る
+    pub fn with_probcut(engine: &Arc<Engine>, max_threads: usize, probcut: bool) -> Arc<SearchShared> {
+        return Arc::new_cyclic(|this| SearchShared {
+            engine: Arc::clone(engine),
+            tt: TranspositionTable::new(TT_SIZE_BITS),
+            abort: AtomicBool::new(false),
+            ordering: OrderingTables::new(),
+            probcut,
+            pool: ThreadPool::new(max_threads),
+            max_threads,
+            busy_threads: AtomicUsize::new(0),
+            this: this.clone(),
+        });
+    }
+
+    pub fn is_aborted(&self) -> bool {
+        return self.abort.load(Ordering::Relaxed);
+    }
+
+    // 同時に探索するスレッド数
+    pub fn max_threads(&self) -> usize {
+        return self.max_threads;
+    }
+
+    // 仕事fをpoolに積む．busy_threadsは積んだときから仕事が終わるまで1つ増える
+    // reservedなら，reserve_threads()ですでに増やしてあるものとする（空きがなくても積むときはfalse）
+    fn queue_job<F>(&self, reserved: bool, f: F) where F: FnOnce(&SearchShared) + Send + 'static {
+        if !reserved {
+            self.busy_threads.fetch_add(1, Ordering::Relaxed);
+        }
+        let shared = self.this.upgrade().expect("SearchShared is alive while searching");
+        self.pool.execute(move || {
+            f(&shared);
+            shared.release_threads(1);
+        });
+    }
+
+    // board_infoの局面をpoolのワーカーでnegamax()で探索し，終わるのを待って(評価値，読み筋)を返す
+    // 呼び出したスレッドは探索しないので，探索するスレッドは常にmax_threads個までとなる
+    pub fn search(&self, alpha: i32, beta: i32, limit: i8, board_info: &BoardInfo, way_of_eval: i8) -> (i32, Vec<u64>) {
+        let (sender, receiver) = mpsc::channel();
+        let mut tmp_board_info = board_info.clone();
+        self.queue_job(false, move |shared| {
+            let mut pv: Vec<u64> = Vec::new();
+            let score = negamax(alpha, beta, limit, &mut tmp_board_info, way_of_eval, shared, &mut pv);
+            sender.send((score, pv)).unwrap();
+        });
+        return receiver.recv().expect("search job has stopped");
+    }
+
+    // 空いているワーカーをwant個まで確保し，確保できた数を返す（確保した数だけqueue_job(true, ・)で仕事を積むこと）
+    // 積まれた仕事の数がワーカー数を超えないように確保するので，確保して積んだ仕事はすぐに始まる
+    fn reserve_threads(&self, want: usize) -> usize {
+        let mut busy = self.busy_threads.load(Ordering::Relaxed);
+        loop {
+            let idle = self.max_threads.saturating_sub(busy);
+            let n = std::cmp::min(want, idle);
+            if n == 0 {
+                return 0;
+            }
+            match self.busy_threads.compare_exchange_weak(busy, busy + n, Ordering::Relaxed, Ordering::Relaxed) {
+                Ok(_) => return n,
+                Err(now) => busy = now,
+            };
+        }
+    }
+
+    fn release_threads(&self, n: usize) {
+        self.busy_threads.fetch_sub(n, Ordering::Relaxed);
+    }
+}
+
+// 兄弟ノードを複数のスレッドで分担して探索するときに共有する情報（分割点）
+struct SplitPoint {
+    board_info: BoardInfo, // 分割した局面
+    beta: i32,
+    limit: i8,
+    way_of_eval: i8,
+    moves: Vec<u64>,       // 分担して調べる手
+    next: AtomicUsize,     // 次に調べる手のインデックス
+    alpha: AtomicI32,      // 現在のα値
+    best: Mutex<(i32, Vec<u64>)>, // (最大の評価値，その手から始まる読み筋)
+    cutoff: AtomicBool,    // βカットが起きたらtrue
+    helpers: Mutex<usize>, // まだ終わっていない手伝いの仕事の数
+    helpers_done: Condvar, // 手伝いの仕事が終わるたびに知らせる
+}
+
+// 分割点の手を1つずつ取り出して探索する（所有スレッドと手伝うスレッドの両方が呼ぶ）
+fn search_split_point(split: &SplitPoint, shared: &SearchShared) {
+    let (beta, limit, way_of_eval) = (split.beta, split.limit, split.way_of_eval);
+    loop {
+        if split.cutoff.load(Ordering::Relaxed) || shared.is_aborted() {
+            return;
+        }
+        let i = split.next.fetch_add(1, Ordering::Relaxed);
+        if i >= split.moves.len() {
+            return;
+        }
+        let mask = split.moves[i];
+        let alpha = split.alpha.load(Ordering::Relaxed);
+
+        let mut tmp_board_info: BoardInfo = split.board_info.clone();
+        let mut child_pv: Vec<u64> = Vec::new();
+        place(mask, &mut tmp_board_info); // 実際においてみる
+        swap(&mut tmp_board_info);
+        let score = scout(alpha, beta, limit, &mut tmp_board_info, way_of_eval, shared, &mut child_pv);
+
+        if shared.is_aborted() {
+            return;
+        }
+        let mut best = split.best.lock().unwrap();
+        if score > best.0 { // 得点が高くなるように更新
+            child_pv.insert(0, mask);
+            *best = (score, child_pv);
+            split.alpha.fetch_max(score, Ordering::Relaxed); // α値更新
+        }
+        if score >= beta { // βカット
+            split.cutoff.store(true, Ordering::Relaxed);
+        }
+    }
+}
+
+// 2手目以降の着手後の局面board_infoを探索し，着手した側からみた評価値を返す（PVS / NegaScout）
+// αを超えるかどうかだけを幅0の窓（null window）で調べ，超えたときだけ通常の窓で再探索する
+fn scout(alpha: i32, beta: i32, limit: i8, board_info: &mut BoardInfo, way_of_eval: i8, shared: &SearchShared, pv: &mut Vec<u64>) -> i32 {
+    let score = -negamax(-alpha-1, -alpha, limit-1, board_info, way_of_eval, shared, pv);
+    if alpha < score && score < beta && !shared.is_aborted() {
+        return -negamax(-beta, -alpha, limit-1, board_info, way_of_eval, shared, pv);
+    }
+    return score;
+}
+
+// Multi-ProbCut: 浅い探索の結果から，深さlimitの探索の評価値が窓(alpha, beta)の外に出ると予測できればその境界を返す
+fn probcut(alpha: i32, beta: i32, limit: i8, board_info: &mut BoardInfo, way_of_eval: i8, shared: &SearchShared) -> Option<i32> {
+    let empties = 64 - (board_info.player_board | board_info.opponent_board).count_ones() as i32;
+    let params = shared.engine.mpc_params(way_of_eval)?; // 今の評価関数と重みについて求めたパラメータがなければカットしない
+    let mut pv: Vec<u64> = Vec::new();
+    for cut in params.cuts(empties, limit) {
+        // 浅い探索の評価値がbound以上なら，深い探索の評価値はほぼ確実にbeta以上
+        let bound = ((beta as f64 + MPC_THRESHOLD * cut.sigma - cut.b) / cut.a).ceil();
+        if bound.abs() < MPC_SCORE_LIMIT as f64 {
+            let bound = bound as i32;
+            if negamax(bound-1, bound, cut.shallow, board_info, way_of_eval, shared, &mut pv) >= bound && !shared.is_aborted() {
+                return Some(beta);
+            }
+        }
+        // 浅い探索の評価値がbound以下なら，深い探索の評価値はほぼ確実にalpha以下
+        let bound = ((alpha as f64 - MPC_THRESHOLD * cut.sigma - cut.b) / cut.a).floor();
+        if bound.abs() < MPC_SCORE_LIMIT as f64 {
+            let bound = bound as i32;
+            if negamax(bound, bound+1, cut.shallow, board_info, way_of_eval, shared, &mut pv) <= bound && !shared.is_aborted() {
+                return Some(alpha);
+            }
+        }
+    }
+    return None;
+}
+
+// pvを，maskに続けてchild_pvをたどる読み筋にする
+fn set_pv(pv: &mut Vec<u64>, mask: u64, child_pv: &[u64]) {
+    pv.clear();
+    pv.push(mask);
+    pv.extend_from_slice(child_pv);
+}
+
+// 探索（alpha-beta法による，2手目以降は幅0の窓で調べるPVS）
+// 最初の手を調べ終わったあと，残りの手はpoolに空いているワーカーがあれば分担して調べる（Young Brothers Wait）
+// pvには，この局面から最善と読んだ手順（読み筋，パスは0）が入る
+// shared.abortがtrueになったときの返り値は意味をもたないので，呼び出し側で捨てること
+pub fn negamax(alpha_: i32, beta_: i32, limit: i8, board_info: &mut BoardInfo, way_of_eval: i8, shared: &SearchShared, pv: &mut Vec<u64>) -> i32 {
+    let mut alpha: i32 = alpha_;
+    let beta: i32 = beta_;
+    let tt = &shared.tt;
+    pv.clear();
+
+    if shared.is_aborted() { // 探索打ち切り
+        return 0;
+    }
+
+    if limit == 0 || is_game_over(board_info) { // 深さ制限 or 終局
+        return shared.engine.evaluate(way_of_eval, &board_info);
+    }
+
+    // 読み切りでは，空きマスが少なくなったら専用の探索に切り替え，それまでは確定石で枝刈りする
+    if way_of_eval == EVAL_WIN || way_of_eval == EVAL_PERFECT {
+        let empties = 64 - (board_info.player_board | board_info.opponent_board).count_ones() as i32;
+        if empties <= ENDGAME_SOLVER_EMPTIES && empties <= limit as i32 {
+            return solve(alpha, beta, board_info, way_of_eval, shared, pv);
+        }
+        if let Some(score) = stability_cutoff(alpha, beta, board_info, way_of_eval) {
+            return score;
+        }
+    }
+
+    let legal_board: u64 = make_legal_board(&board_info);
+    let mut score: i32;
+
+    if legal_board.count_ones() == 0 as u32 { // パス
+        let tmp_board_info: BoardInfo = board_info.clone();
+        swap(board_info);
+        score = -negamax(-beta, -alpha, limit, board_info, way_of_eval, shared, pv); // さらに奥を深さを変えずに探索
+        *board_info = tmp_board_info; // 盤面を元に戻す
+        pv.insert(0, 0);
+        return score;
+    }
+
+    // 置換表を調べる（末端付近では表を引くコストの方が大きいので使わない）
+    let use_tt = limit >= TT_MIN_DEPTH;
+    let hash = if use_tt {zobrist_hash(board_info)} else {0};
+    let mut tt_move: u64 = 0;
+    if let Some(entry) = if use_tt {tt.probe(hash)} else {None} {
+        if entry.depth >= limit && (entry.bound == BOUND_EXACT
+            || (entry.bound == BOUND_LOWER && entry.score >= beta)
+            || (entry.bound == BOUND_UPPER && entry.score <= alpha)) {
+            if entry.best_move & legal_board != 0 { // 置換表からは読み筋の最初の手しか分からない
+                pv.push(entry.best_move);
+            }
+            return entry.score;
+        }
+        tt_move = entry.best_move & legal_board;
+    }
+
+    // 中盤では，浅い探索で窓の外に出ると予測できた部分木を省略する
+    if (way_of_eval == EVAL_NORMAL || way_of_eval == EVAL_PATTERN) && shared.probcut && limit >= MPC_MIN_DEPTH {
+        if let Some(score) = probcut(alpha, beta, limit, board_info, way_of_eval, shared) {
+            return score;
+        }
+    }
+
+    // 手をよさそうな順に並べる
+    let mut moves = [0u64; 64];
+    let move_count = order_moves(board_info, legal_board, tt_move, limit, way_of_eval, &shared.ordering, &mut moves);
+
+    let mut score_max: i32 = std::i32::MIN;
+    let mut best_move: u64 = 0;
+    let mut child_pv: Vec<u64> = Vec::new();
+    let mut i = 0;
+
+    while i < move_count {
+        let mask: u64 = moves[i];
+        i += 1;
+
+        let tmp_board_info: BoardInfo = board_info.clone();
+        place(mask, board_info); // 実際においてみる
+        swap(board_info);
+        score = if i == 1 {
+            -negamax(-beta, -alpha, limit-1, board_info, way_of_eval, shared, &mut child_pv)
+        }else{
+            scout(alpha, beta, limit, board_info, way_of_eval, shared, &mut child_pv)
+        };
+        *board_info = tmp_board_info; // 盤面を元に戻す
+
+        if shared.is_aborted() { // 打ち切られた探索の結果は置換表に残さない
+            return 0;
+        }
+
+        if score >= beta { // βカット
+            if use_tt {
+                tt.store(hash, score, limit, BOUND_LOWER, mask);
+            }
+            shared.ordering.record_cutoff(mask, limit);
+            set_pv(pv, mask, &child_pv);
+            return score;
+        }
+        if score > score_max { // 得点が高くなるように更新
+            score_max = score;
+            best_move = mask;
+            alpha = max(alpha, score_max); // α値更新
+            set_pv(pv, mask, &child_pv);
+        }
+
+        // 最初の手で得られたα値を使って，残りの手を空いているスレッドと分担して調べる
+        if limit >= SPLIT_MIN_DEPTH && move_count - i >= 2 {
+            let helpers = shared.reserve_threads(move_count - i - 1);
+            if helpers > 0 {
+                let split = Arc::new(SplitPoint {
+                    board_info: board_info.clone(),
+                    beta,
+                    limit,
+                    way_of_eval,
+                    moves: moves[i..move_count].to_vec(),
+                    next: AtomicUsize::new(0),
+                    alpha: AtomicI32::new(alpha),
+                    best: Mutex::new((score_max, pv.clone())),
+                    cutoff: AtomicBool::new(false),
+                    helpers: Mutex::new(helpers),
+                    helpers_done: Condvar::new(),
+                });
+                i = move_count;
+                for _ in 0..helpers {
+                    let split = Arc::clone(&split);
+                    shared.queue_job(true, move |shared| {
+                        search_split_point(&split, shared);
+                        *split.helpers.lock().unwrap() -= 1;
+                        split.helpers_done.notify_all();
+                    });
+                }
+                search_split_point(&split, shared);
+                // 手伝いの仕事がすべて終わるのを待つ（確保したワーカーで積んだので，待っていればすぐに始まって終わる）
+                let mut running = split.helpers.lock().unwrap();
+                while *running > 0 {
+                    running = split.helpers_done.wait(running).unwrap();
+                }
+                drop(running);
+
+                if shared.is_aborted() {
+                    return 0;
+                }
+                let best = split.best.lock().unwrap();
+                score_max = best.0;
+                best_move = best.1[0];
+                pv.clone_from(&best.1);
+                if score_max >= beta { // βカット
+                    if use_tt {
+                        tt.store(hash, score_max, limit, BOUND_LOWER, best_move);
+                    }
+                    shared.ordering.record_cutoff(best_move, limit);
+                    return score_max;
+                }
+            }
+        }
+    }
+
+    if use_tt {
+        let bound = if score_max > alpha_ {BOUND_EXACT} else {BOUND_UPPER};
+        tt.store(hash, score_max, limit, bound, best_move);
+    }
+
+    return score_max;
+}
+
+// 1手に使う時間(ms)を，残り時間と空きマス数から決める
+pub fn allocate_time(engine: &Engine, board_info: &BoardInfo, left_time: i32, way_of_eval: i8) -> i32 {
+    let usable = max(left_time - TIME_MARGIN, 0);
+    let empties = 64 - (board_info.player_board | board_info.opponent_board).count_ones() as i32;
+    if way_of_eval == EVAL_NORMAL || way_of_eval == EVAL_PATTERN {
+        // 終盤の読み切りのための時間を残しつつ，終盤に入るまでの自分の手数で割る
+        let moves = max((empties - engine.config.depth(EVAL_WIN) as i32) / 2, 0) + ENDGAME_TIME_SHARE;
+        return usable / moves;
+    }
+    // 読み切りでは，残り時間のENDGAME_TIME_SHARE分の1までを使う
+    return usable / ENDGAME_TIME_SHARE;
+}
+
+// ルートの全合法手を探索窓(alpha, beta)，limitの深さで探索する，返り値は(最善手，評価値，読み筋)
+// 評価値がalpha以下ならすべての手がalpha以下，beta以上なら最善手がbeta以上であることだけが分かる
+// first_move（なければ並べ替えで先頭になった手）を先に1つだけ探索してα値を決め，残りの手はそのα値でsharedのpoolのワーカーが分担して探索する
+// deadlineまでに探索が終わらなければNoneを返す（deadlineがNoneなら終わるまで待つ）．いずれの場合も積んだ仕事がすべて終わってから戻る
+#[allow(clippy::too_many_arguments)]
+fn search_root(board_info: &BoardInfo, first_move: u64, alpha: i32, beta: i32, way_of_eval: i8, limit: i8, shared: &SearchShared, deadline: Option<Instant>) -> Option<(u64, i32, Vec<u64>)> {
+    let legal_board: u64 = make_legal_board(board_info);
+    let mut ret: u64 = 0;
+    let mut max_eval: i32 = std::i32::MIN;
+    let mut best_pv: Vec<u64> = Vec::new();
+
+    shared.abort.store(false, Ordering::Relaxed);
+    let root_alpha = Arc::new(AtomicI32::new(alpha));
+
+    // ワーカー -> メインスレッドのチャンネル
+    let (sender, receiver) = mpsc::channel();
+
+    // ルートの1手を探索する仕事をpoolに積む，結果は(手，評価値，その手から始まる読み筋)としてsenderに送られる
+    // 最初の手以外はroot_alphaより良い手かどうかだけが分かればよいので，幅0の窓で調べる
+    let queue_root_move = |mask: u64, first: bool| {
+        let root_alpha = Arc::clone(&root_alpha);
+        let sender = sender.clone();
+        let mut tmp_board_info = board_info.clone();
+        shared.queue_job(false, move |shared| {
+            place(mask, &mut tmp_board_info);
+            swap(&mut tmp_board_info);
+            let alpha = root_alpha.load(Ordering::Relaxed);
+            let mut pv: Vec<u64> = Vec::new();
+            let tmp = if first {
+                -negamax(-beta, -alpha, limit-1, &mut tmp_board_info, way_of_eval, shared, &mut pv)
+            }else{
+                scout(alpha, beta, limit, &mut tmp_board_info, way_of_eval, shared, &mut pv)
+            };
+            pv.insert(0, mask);
+            if !shared.is_aborted() {
+                root_alpha.fetch_max(tmp, Ordering::Relaxed);
+                println!("debug: score={}, pv={}", tmp, line_to_string(&pv));
+            }
+            // メインスレッドは全ての結果を受け取るまで待つので，受信側はまだ残っている
+            sender.send((mask, tmp, pv)).unwrap();
+        });
+    };
+
+    // 手をよさそうな順に並べ，最初の手だけ先に積む
+    let mut moves = [0u64; 64];
+    let move_count = order_moves(board_info, legal_board, first_move, limit, way_of_eval, &shared.ordering, &mut moves);
+    queue_root_move(moves[0], true);
+    let mut job_count = 1;
+    let mut queued_count = 1;
+
+    // 集計
+    let mut result = None;
+    let mut finished_job_count = 0;
+    while finished_job_count != job_count {
+        let received = match deadline {
+            Some(deadline) => {
+                let now = Instant::now();
+                if now >= deadline { // 時間切れ，この深さの結果は使わない
+                    println!("debug: time is up: stop searching");
+                    break;
+                }
+                receiver.recv_timeout(deadline - now)
+            },
+            None => receiver.recv().map_err(|_| mpsc::RecvTimeoutError::Disconnected),
+        };
+        match received {
+            Ok((bit, tmp, pv)) => {
+                finished_job_count += 1;
+
+                if way_of_eval == EVAL_WIN && tmp == 1 { // 必勝読みでは，必勝できる手が見つかったら探索を打ち切ってその手を打つ
+                    println!("Win-Road found: stop searching");
+                    result = Some((bit, tmp, pv));
+                    break;
+                }
+                if tmp >= beta { // 探索窓を上に外れた，残りの手を調べる必要はない
+                    result = Some((bit, tmp, pv));
+                    break;
+                }
+
+                // 得点が高くなるように更新
+                if tmp > max_eval {
+                    max_eval = tmp;
+                    ret = bit;
+                    best_pv = pv;
+                }
+
+                // 最初の手の結果が出たら，残りの手を積む
+                while queued_count < move_count {
+                    queue_root_move(moves[queued_count], false);
+                    queued_count += 1;
+                    job_count += 1;
+                }
+            },
+            Err(mpsc::RecvTimeoutError::Timeout) => continue,
+            Err(mpsc::RecvTimeoutError::Disconnected) => panic!("Non-expected error occured"),
+        };
+    }
+    if finished_job_count == job_count && result.is_none() {
+        result = Some((ret, max_eval, best_pv));
+    }
+
+    // 残っている仕事を打ち切り，すべて終わるのを待つ
+    shared.abort.store(true, Ordering::Relaxed);
+    while finished_job_count != job_count {
+        receiver.recv().unwrap();
+        finished_job_count += 1;
+    }
+
+    return result;
+}
+
+// 深さ1から順に深くしながら探索する（反復深化）
+// 常に1つ前の深さで求めた最善手を保持しておき，deadlineを過ぎたらそれを返す
+// 深さ1はdeadlineを過ぎても必ず調べ終えるので，返り値は常に探索し終えた結果になる
+fn iterative_deepening(shared: &SearchShared, board_info: &BoardInfo, way_of_eval: i8, limit: i8, deadline: Instant) -> (u64, i32, Vec<u64>) {
+    let mut best: Option<(u64, i32, Vec<u64>)> = None;
+
+    for depth in 1..(limit+1) {
+        let iteration_start = Instant::now();
+        let depth_deadline = if depth == 1 {None} else {Some(deadline)};
+
+        // 1つ前の深さの評価値のまわりの狭い窓で探索し，外れたら窓を広げて探索し直す（aspiration window）
+        let mut delta = ASPIRATION_WINDOW;
+        let (mut alpha, mut beta) = match &best {
+            None => (std::i32::MIN+1, std::i32::MAX), // int_maxやint_minをnegateするとoverflowが発生するため，値を調節している
+            Some(best) => (best.1.saturating_sub(delta), best.1.saturating_add(delta)),
+        };
+        let mut finished = false;
+        while let Some(result) = search_root(board_info, best.as_ref().map_or(0, |best| best.0), alpha, beta, way_of_eval, depth, shared, depth_deadline) {
+            delta = delta.saturating_mul(ASPIRATION_WIDEN);
+            if result.1 <= alpha && alpha > std::i32::MIN+1 { // 窓を下に外れた
+                alpha = max(result.1.saturating_sub(delta), std::i32::MIN+1);
+            }else if result.1 >= beta && beta < std::i32::MAX { // 窓を上に外れた，この手は1つ前の最善手より良いので保持しておく
+                beta = result.1.saturating_add(delta);
+                best = Some(result);
+            }else{
+                best = Some(result);
+                finished = true;
+                break;
+            }
+        }
+        if !finished {
+            break;
+        }
+        let (_, score, pv) = best.as_ref().unwrap();
+        println!("debug: depth={}, score={}, pv={}", depth, score, line_to_string(pv));
+
+        // 次の深さが時間内に終わりそうにないなら打ち切る
+        let now = Instant::now();
+        if now >= deadline || now + iteration_start.elapsed() * BRANCHING_FACTOR > deadline {
+            break;
+        }
+    }
+
+    return best.expect("the search of depth 1 always finishes");
+}
+
+// 1局を通して使う探索の状態
+// 評価関数ごとのSearchShared（置換表，手の並べ替えの情報，スレッドのpool）を初めて使うときに作り，手ごとに作り直さずに使い回す
+pub struct Searcher {
+    engine: Arc<Engine>,
+    shared: Vec<(i8, Arc<SearchShared>)>, // (評価関数，その評価関数で探索するSearchShared)，置換表は評価関数ごとに別のものを用いるため
+}
+
+impl Searcher {
+    // engineで探索するSearcherを作る（対局ごとに作ること）
+    pub fn new(engine: &Arc<Engine>) -> Searcher {
+        return Searcher {
+            engine: Arc::clone(engine),
+            shared: Vec::new(),
+        };
+    }
+
+    pub fn engine(&self) -> &Arc<Engine> {
+        return &self.engine;
+    }
+
+    // 評価関数way_of_evalで探索するSearchShared（まだなければ作る）
+    pub fn shared(&mut self, way_of_eval: i8) -> Arc<SearchShared> {
+        if let Some((_, shared)) = self.shared.iter().find(|(n, _)| *n == way_of_eval) {
+            return Arc::clone(shared);
+        }
+        let shared = SearchShared::new(&self.engine, self.engine.thread_count());
+        self.shared.push((way_of_eval, Arc::clone(&shared)));
+        return shared;
+    }
+}
+
+// searcherのエンジンで着手する手を思考する，返り値は(打つ手，その手の評価値，読み筋)
+pub fn decide(searcher: &mut Searcher, board_info: &mut BoardInfo, left_time: i32, way_of_eval: i8, limit: i8) -> (u64, i32, Vec<u64>) {
+    let engine = Arc::clone(searcher.engine());
+    let legal_board: u64 = make_legal_board(&board_info);
+
+    if legal_board == 0 as u64 { // おける手がなければパスを選択
+        return (0 as u64, engine.evaluate(way_of_eval, &board_info), vec![0]);
+    }
+
+    if legal_board.count_ones() == 1 as u32 { // おける手が一つしかなければそのままそれを返す
+        return (legal_board, engine.evaluate(way_of_eval, &board_info), vec![legal_board]);
+    }
+
+    // 定石にある局面なら探索せずに定石の手を打つ
+    let mut rng = Rng::new(SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_nanos() as u64).unwrap_or(0));
+    if let Some(place_bit) = engine.book.choose(board_info, engine.config.book_randomness, &mut rng) {
+        println!("debug: book move: {}", move_to_string(place_bit));
+        return (place_bit, engine.evaluate(way_of_eval, &board_info), vec![place_bit]);
+    }
+
+    // 実行速度計測開始
+    let start = Instant::now();
+    let time_budget = allocate_time(&engine, board_info, left_time, way_of_eval);
+    let deadline = start + Duration::from_millis(time_budget as u64);
+
+    println!("debug: left_time={}, way_of_eval={}, limit={}, time_budget={}", left_time, way_of_eval, limit, time_budget);
+
+    if way_of_eval == EVAL_NORMAL || way_of_eval == EVAL_PATTERN || way_of_eval == EVAL_BY_POINTTABLE {
+        let result = iterative_deepening(&searcher.shared(way_of_eval), board_info, way_of_eval, limit, deadline);
+        println!("debug: expected line: {} (score={})", line_to_string(&result.2), result.1);
+        return result;
+    }
+
+    // 必勝読みや完全読みでは，読み切れなかったときのために先に中盤の評価関数で手を用意しておく
+    let normal_deadline = start + Duration::from_millis((time_budget / NORMAL_SEARCH_SHARE) as u64);
+    let midgame_evaluator = engine.midgame_evaluator();
+    let normal_result = iterative_deepening(&searcher.shared(midgame_evaluator), board_info, midgame_evaluator, choose_limit(&engine, midgame_evaluator), normal_deadline);
+
+    let shared = searcher.shared(way_of_eval);
+    // 読み切りの評価値は必勝読みなら-1〜1，完全読みなら-64〜64に収まるので，その範囲を探索窓にする
+    let (alpha, beta) = if way_of_eval == EVAL_WIN {(-1, 1)} else {(-BOARDSIZE, BOARDSIZE)};
+    let (ret, max_eval, pv) = match search_root(board_info, normal_result.0, alpha, beta, way_of_eval, limit, &shared, Some(deadline)) {
+        Some(result) => result,
+        None => {
+            println!("debug: could not finish reading to the end; use the result of the midgame search");
+            println!("debug: expected line: {} (score={})", line_to_string(&normal_result.2), normal_result.1);
+            return normal_result;
+        },
+    };
+
+    // 勝敗予想
+    if way_of_eval == EVAL_WIN {
+        match max_eval {
+            1  => println!("I predict I will win."),
+            0  => println!("I predict this game will end in a draw."),
+            -1 => println!("I predict I will lose."),
+            _  => panic!("Invalid value"),
+        };
+    }else if way_of_eval == EVAL_PERFECT {
+        if max_eval > 0 {
+            println!("I predict I will win by {} points.", max_eval);
+        }else if max_eval == 0 {
+            println!("I predict this game will end in a draw.");
+        }else{
+            println!("I predict I will lose by {} points.", -max_eval);
+        }
+    }
+
+    println!("debug: expected line: {} (score={})", line_to_string(&pv), max_eval);
+
+    return (ret, max_eval, pv);
+}
+
+// analyze()で求めた，1つの手の評価
+pub struct MoveAnalysis {
+    pub place_bit: u64, // 手（0ならパス）
+    pub score: i32,     // 打った側からみた評価値
+    pub pv: Vec<u64>,   // 打った手から始まる読み筋
+}
+
+// 対局の外で局面をengineで検討する: 全合法手をそれぞれ全幅の窓で探索し，評価値の高い順に並べる
+// 深さ1から（読み切りではlimitから）順に深くし，time_limit(ms)までに全ての手を調べ終えた最も深い結果を返す
+// 返り値は(深さ，各手の評価)，終局している局面では手がないので(0, 空)，time_limitまでに1つも深さを調べ終えられなかったときはNone
+pub fn analyze(engine: &Arc<Engine>, board_info: &BoardInfo, way_of_eval: i8, limit: i8, time_limit: u64) -> Option<(i8, Vec<MoveAnalysis>)> {
+    if is_game_over(board_info) {
+        return Some((0, Vec::new()));
+    }
+    let mut moves = Vec::<u64>::new();
+    let mut legal_board = make_legal_board(board_info);
+    if legal_board == 0 {
+        moves.push(0); // パスするしかない
+    }
+    while legal_board != 0 {
+        let mask = legal_board & legal_board.wrapping_neg();
+        legal_board ^= mask;
+        moves.push(mask);
+    }
+
+    let shared = SearchShared::new(engine, engine.thread_count());
+    let deadline = Instant::now() + Duration::from_millis(time_limit);
+    let first_depth = if way_of_eval == EVAL_WIN || way_of_eval == EVAL_PERFECT {limit} else {1};
+    let mut result: Option<(i8, Vec<MoveAnalysis>)> = None;
+    thread::scope(|scope| {
+        // deadlineを過ぎたら探索を打ち切らせる（探索が先に終わればsenderが捨てられて戻る）
+        let (sender, receiver) = mpsc::channel::<()>();
+        let shared_ref: &SearchShared = &shared;
+        scope.spawn(move || {
+            if let Err(mpsc::RecvTimeoutError::Timeout) = receiver.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+                shared_ref.abort.store(true, Ordering::Relaxed);
+            }
+        });
+
+        for depth in first_depth..(limit+1) {
+            let mut analyses = Vec::<MoveAnalysis>::new();
+            for &mask in moves.iter() {
+                let mut tmp_board_info = board_info.clone();
+                place(mask, &mut tmp_board_info);
+                swap(&mut tmp_board_info);
+                let (score, mut pv) = shared.search(std::i32::MIN+1, std::i32::MAX, depth-1, &tmp_board_info, way_of_eval);
+                let score = -score;
+                if shared.is_aborted() {
+                    break;
+                }
+                pv.insert(0, mask);
+                analyses.push(MoveAnalysis {
+                    place_bit: mask,
+                    score,
+                    pv,
+                });
+            }
+            if shared.is_aborted() { // 時間切れ，この深さの結果は使わない
+                break;
+            }
+            analyses.sort_by_key(|analysis| std::cmp::Reverse(analysis.score));
+            result = Some((depth, analyses));
+        }
+        drop(sender);
+    });
+    return result;
+}
+
+// evaluatorごとの探索の深さを返す（engineの設定から）
+pub fn choose_limit(engine: &Engine, way_of_eval: i8) -> i8 {
+    return engine.config.depth(way_of_eval);
+}
+
+// board_infoの状況に合わせて適切なevaluatorを選択する
+// 読み切りは空きマス数が読む深さ以下のときだけ選ぶ（深さが足りないと終局していない局面を読み切りの値として返してしまう）
+pub fn choose_evaluator(engine: &Engine, board_info: &BoardInfo) -> i8 {
+    let empties = 64 - (board_info.player_board | board_info.opponent_board).count_ones() as i8;
+    if empties <= engine.config.depth(EVAL_PERFECT) {
+        return EVAL_PERFECT;
+    }
+    if empties <= engine.config.depth(EVAL_WIN) {
+        return EVAL_WIN;
+    }
+    return engine.midgame_evaluator();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::random::random_game;
+
+    // ランダムに打って空きマスがempties個になった，終局していない局面をcount個
+    fn positions_with_empties(empties: u32, count: usize, rng: &mut Rng) -> Vec<BoardInfo> {
+        let mut positions = Vec::<BoardInfo>::new();
+        while positions.len() < count {
+            let (game, _) = random_game(rng);
+            let found = game.into_iter().find(|board_info| (board_info.player_board | board_info.opponent_board).count_zeros() == empties);
+            if let Some(board_info) = found.filter(|board_info| !is_game_over(board_info)) {
+                positions.push(board_info);
+            }
+        }
+        return positions;
+    }
+
+    // 読み切りの深さを小さくしたエンジン
+    fn engine_with_solve_depths(perfect: &str, win: &str) -> Engine {
+        let mut engine = Engine::new();
+        engine.config.set("EVAL_PERFECT_DEPTH", perfect).unwrap();
+        engine.config.set("EVAL_WIN_DEPTH", win).unwrap();
+        engine.set_thread_count(2);
+        return engine;
+    }
+
+    #[test]
+    fn choose_evaluator_switches_at_the_configured_empties() {
+        let engine = engine_with_solve_depths("4", "6");
+        let mut rng = Rng::new(15);
+        for &(empties, expected) in [(3, EVAL_PERFECT), (4, EVAL_PERFECT), (5, EVAL_WIN), (6, EVAL_WIN), (7, EVAL_NORMAL)].iter() {
+            for board_info in positions_with_empties(empties, 10, &mut rng).iter() {
+                assert_eq!(choose_evaluator(&engine, board_info), expected, "{}", board_info_to_string(board_info));
+            }
+        }
+    }
+
+    #[test]
+    fn analyze_at_the_solve_depths_matches_solve() {
+        // 空きマスが読み切りの深さちょうどの局面と，それより1つ多い局面
+        let engine = Arc::new(engine_with_solve_depths("6", "7"));
+        let shared = SearchShared::new(&engine, 1);
+        let mut rng = Rng::new(1515);
+        for &(empties, expected) in [(6, EVAL_PERFECT), (7, EVAL_WIN)].iter() {
+            for board_info in positions_with_empties(empties, 10, &mut rng).iter() {
+                let way_of_eval = choose_evaluator(&engine, board_info);
+                assert_eq!(way_of_eval, expected);
+                let (_, analyses) = analyze(&engine, board_info, way_of_eval, choose_limit(&engine, way_of_eval), 60000).unwrap();
+                assert!(!analyses.is_empty());
+                for analysis in analyses.iter() {
+                    let mut tmp_board_info = board_info.clone();
+                    place(analysis.place_bit, &mut tmp_board_info);
+                    swap(&mut tmp_board_info);
+                    let exact = -solve(-BOARDSIZE, BOARDSIZE, &tmp_board_info, way_of_eval, &shared, &mut Vec::new());
+                    assert_eq!(analysis.score, exact, "{} {}", board_info_to_string(board_info), move_to_string(analysis.place_bit));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn parallel_and_serial_searches_match_solve() {
+        // 置換表・手の並べ替え・兄弟ノードの分担を通る空きマス数（ENDGAME_SOLVER_EMPTIESより多い）の局面で比べる
+        let engine = Arc::new(Engine::new());
+        let solver = SearchShared::new(&engine, 1);
+        let mut rng = Rng::new(909);
+        let mut positions = Vec::<(BoardInfo, i32)>::new();
+        for empties in 10..15 {
+            for board_info in positions_with_empties(empties, 2, &mut rng) {
+                let exact = solve(-BOARDSIZE, BOARDSIZE, &board_info, EVAL_PERFECT, &solver, &mut Vec::new());
+                positions.push((board_info, exact));
+            }
+        }
+        for &way_of_eval in [EVAL_PERFECT, EVAL_WIN].iter() {
+            // 置換表は評価関数ごとに別のものを用いる
+            let serial = SearchShared::new(&engine, 1);
+            let parallel = SearchShared::new(&engine, 4);
+            for (board_info, exact) in positions.iter() {
+                let expected = if way_of_eval == EVAL_WIN {exact.signum()} else {*exact};
+                let limit = 64 - (board_info.player_board | board_info.opponent_board).count_ones() as i8;
+                for shared in [&serial, &parallel].iter() {
+                    let (score, pv) = shared.search(std::i32::MIN+1, std::i32::MAX, limit, board_info, way_of_eval);
+                    assert_eq!(score, expected, "{} way_of_eval={} threads={}", board_info_to_string(board_info), way_of_eval, shared.max_threads());
+                    assert!(!pv.is_empty());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn searcher_reuses_one_shared_per_evaluator() {
+        let mut engine = Engine::new();
+        engine.set_thread_count(1);
+        let mut searcher = Searcher::new(&Arc::new(engine));
+        let normal = searcher.shared(EVAL_NORMAL);
+        assert!(Arc::ptr_eq(&normal, &searcher.shared(EVAL_NORMAL)));
+        assert!(!Arc::ptr_eq(&normal, &searcher.shared(EVAL_PERFECT)));
+
+        // 同じSearcherで続けて読み切っても（置換表が残っていても）結果は変わらない
+        let solver = SearchShared::new(searcher.engine(), 1);
+        let mut rng = Rng::new(1717);
+        for board_info in positions_with_empties(10, 3, &mut rng).iter() {
+            if make_legal_board(board_info).count_ones() < 2 { // 手が1つ以下なら探索しない
+                continue;
+            }
+            let exact = solve(-BOARDSIZE, BOARDSIZE, board_info, EVAL_PERFECT, &solver, &mut Vec::new());
+            for _ in 0..2 {
+                let (place_bit, score, _) = decide(&mut searcher, &mut board_info.clone(), 20000, EVAL_PERFECT, 10);
+                assert_eq!(score, exact, "{}", board_info_to_string(board_info));
+                assert_ne!(place_bit & make_legal_board(board_info), 0);
+            }
+        }
+    }
+}
@@ -0,0 +1,67 @@
+// ネットワークを使わずに，エンジン同士で対戦させる（自己対戦）
+
+use std::sync::Arc;
+use std::time::Instant;
+
+use crate::board::*;
+use crate::engine::Engine;
+use crate::search::*;
+
+// 1手分の記録
+pub struct MoveRecord {
+    pub now_turn: i8,     // 打った側の色
+    pub place_bit: u64,   // 打った手（0ならパス）
+    pub eval: i32,        // 打った側から見た評価値
+    pub way_of_eval: i8,  // 用いた評価関数
+    pub elapsed_ms: u128, // 思考時間
+    pub pv: Vec<u64>,     // 打った手から始まる読み筋
+}
+
+// 1局分の記録
+pub struct GameRecord {
+    pub moves: Vec<MoveRecord>,
+    pub black_count: u32,
+    pub white_count: u32,
+    pub superior: i8,
+}
+
+// board_infoの局面から終局まで，engineに黒白それぞれ持ち時間time_limit(ms)で自己対戦させる
+pub fn selfplay(engine: &Arc<Engine>, board_info: &BoardInfo, time_limit: i32) -> GameRecord {
+    let mut board_info = board_info.clone();
+    let mut left_time = [time_limit, time_limit]; // [黒，白]
+    let mut moves = Vec::<MoveRecord>::new();
+    let mut searcher = Searcher::new(engine); // 黒白とも同じエンジンなので，置換表なども共有する
+
+    while !is_game_over(&board_info) {
+        let side = if board_info.now_turn == BLACK {0} else {1};
+        let way_of_eval = choose_evaluator(engine, &board_info);
+        let limit = choose_limit(engine, way_of_eval);
+
+        let start = Instant::now();
+        let (place_bit, eval, pv) = decide(&mut searcher, &mut board_info, left_time[side], way_of_eval, limit);
+        let elapsed_ms = start.elapsed().as_millis();
+        left_time[side] -= elapsed_ms as i32;
+
+        moves.push(MoveRecord {
+            now_turn: board_info.now_turn,
+            place_bit,
+            eval,
+            way_of_eval,
+            elapsed_ms,
+            pv,
+        });
+
+        if place(place_bit, &mut board_info) == PLACE_ERR {
+            panic!("decide returned an illegal move: {}", move_to_string(place_bit));
+        }
+        swap(&mut board_info);
+    }
+
+    let (black_count, white_count, superior) = get_result(&board_info);
+    return GameRecord {
+        moves,
+        black_count,
+        white_count,
+        superior,
+    };
+}
@@ -0,0 +1,176 @@
+// 盤面の対称変換（回転・反転）
+// 盤面（u64）と手（1ビットだけ立ったu64，パスは0のまま）に同じように施せる
+// 8通りの対称変換のうち盤面が最小になるもの（正規形）を使えば，対称な局面を同じものとして扱える
+
+use crate::board::BoardInfo;
+
+// 対称変換の数（恒等変換を含む）
+pub const SYMMETRIES: usize = 8;
+
+// 上下反転（1行目と8行目を入れ替える）
+pub fn flip_vertical(board: u64) -> u64 {
+    return board.swap_bytes();
+}
+
+// 左右反転（A列とH列を入れ替える）
+pub fn flip_horizontal(board: u64) -> u64 {
+    return board.reverse_bits().swap_bytes();
+}
+
+// A1-H8の対角線で反転（行と列を入れ替える）
+pub fn flip_diagonal(board: u64) -> u64 {
+    let mut board = board;
+    let mut tmp = 0x0f0f0f0f00000000 & (board ^ (board << 28));
+    board ^= tmp ^ (tmp >> 28);
+    tmp = 0x3333000033330000 & (board ^ (board << 14));
+    board ^= tmp ^ (tmp >> 14);
+    tmp = 0x5500550055005500 & (board ^ (board << 7));
+    board ^= tmp ^ (tmp >> 7);
+    return board;
+}
+
+// H1-A8の対角線で反転
+pub fn flip_anti_diagonal(board: u64) -> u64 {
+    return rotate_180(flip_diagonal(board));
+}
+
+// 時計回りに90度回転（A1がH1に移る）
+pub fn rotate_90(board: u64) -> u64 {
+    return flip_horizontal(flip_diagonal(board));
+}
+
+// 180度回転（A1がH8に移る）
+pub fn rotate_180(board: u64) -> u64 {
+    return board.reverse_bits();
+}
+
+// 反時計回りに90度回転（A1がA8に移る）
+pub fn rotate_270(board: u64) -> u64 {
+    return flip_vertical(flip_diagonal(board));
+}
+
+// 8通りの対称変換のk番目を施す（対角線で反転したあと，kのビットに応じて左右・上下に反転する）
+// k=0は恒等変換，1は左右反転，2は上下反転，3は180度回転，4は対角線で反転，5は時計回りに90度回転，6は反時計回りに90度回転，7はH1-A8の対角線で反転
+pub fn transform(k: usize, board: u64) -> u64 {
+    let board = if k & 4 != 0 {flip_diagonal(board)} else {board};
+    let board = if k & 1 != 0 {flip_horizontal(board)} else {board};
+    let board = if k & 2 != 0 {flip_vertical(board)} else {board};
+    return board;
+}
+
+// transform(k, ・)の逆変換
+pub fn inverse_transform(k: usize, board: u64) -> u64 {
+    let board = if k & 2 != 0 {flip_vertical(board)} else {board};
+    let board = if k & 1 != 0 {flip_horizontal(board)} else {board};
+    let board = if k & 4 != 0 {flip_diagonal(board)} else {board};
+    return board;
+}
+
+// 局面の正規形（8通りの対称変換のうち(player_board, opponent_board)が最小のもの）と，そのための変換の番号
+pub fn canonical(board_info: &BoardInfo) -> ((u64, u64), usize) {
+    let mut best = ((board_info.player_board, board_info.opponent_board), 0);
+    for k in 1..SYMMETRIES {
+        let key = (transform(k, board_info.player_board), transform(k, board_info.opponent_board));
+        if key < best.0 {
+            best = (key, k);
+        }
+    }
+    return best;
+}
+
+// board_infoの局面でのplace_bitの手を，正規形の局面での手にする
+// 初期局面のように局面自体が対称なときは，正規形にうつる変換が複数あるので，うつした手のうち最小のものにそろえる
+pub fn canonical_move(board_info: &BoardInfo, place_bit: u64) -> u64 {
+    let (key, _) = canonical(board_info);
+    return (0..SYMMETRIES).filter(|&k| (transform(k, board_info.player_board), transform(k, board_info.opponent_board)) == key)
+        .map(|k| transform(k, place_bit)).min().unwrap();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::*;
+    use crate::random::{random_game, Rng};
+
+    fn square(s: &str) -> u64 {
+        return string_to_move(s).unwrap();
+    }
+
+    #[test]
+    fn transforms_move_squares_as_documented() {
+        assert_eq!(rotate_90(square("A1")), square("H1"));
+        assert_eq!(rotate_180(square("A1")), square("H8"));
+        assert_eq!(rotate_270(square("A1")), square("A8"));
+        assert_eq!(flip_vertical(square("B1")), square("B8"));
+        assert_eq!(flip_horizontal(square("B1")), square("G1"));
+        assert_eq!(flip_diagonal(square("B1")), square("A2"));
+        assert_eq!(flip_anti_diagonal(square("B1")), square("H7"));
+        assert_eq!(transform(0, square("B1")), square("B1"));
+        assert_eq!(transform(5, square("A1")), square("H1"));
+        assert_eq!(transform(6, square("A1")), square("A8"));
+        assert_eq!(transform(7, square("B1")), square("H7"));
+    }
+
+    #[test]
+    fn inverse_transform_undoes_transform() {
+        let mut rng = Rng::new(20);
+        for _ in 0..100 {
+            let board = rng.next_u64();
+            for k in 0..SYMMETRIES {
+                assert_eq!(inverse_transform(k, transform(k, board)), board, "k={}", k);
+                assert_eq!(transform(k, inverse_transform(k, board)), board, "k={}", k);
+            }
+        }
+        for k in 0..SYMMETRIES {
+            assert_eq!(transform(k, 0), 0); // パスはパスのまま
+        }
+    }
+
+    #[test]
+    fn transforms_are_distinct_and_keep_legal_moves() {
+        let mut rng = Rng::new(2020);
+        let board = rng.next_u64();
+        let mut images: Vec<u64> = (0..SYMMETRIES).map(|k| transform(k, board)).collect();
+        images.sort();
+        images.dedup();
+        assert_eq!(images.len(), SYMMETRIES);
+
+        for board_info in random_game(&mut rng).0.iter() {
+            for k in 0..SYMMETRIES {
+                let mut transformed = board_info.clone();
+                transformed.player_board = transform(k, board_info.player_board);
+                transformed.opponent_board = transform(k, board_info.opponent_board);
+                assert_eq!(make_legal_board(&transformed), transform(k, make_legal_board(board_info)));
+            }
+        }
+    }
+
+    #[test]
+    fn symmetric_positions_share_canonical_form_and_moves() {
+        let mut rng = Rng::new(202);
+        for board_info in random_game(&mut rng).0.iter() {
+            let (key, k) = canonical(board_info);
+            assert_eq!(key, (transform(k, board_info.player_board), transform(k, board_info.opponent_board)));
+            let legal_board = make_legal_board(board_info);
+            for j in 0..SYMMETRIES {
+                let mut transformed = board_info.clone();
+                transformed.player_board = transform(j, board_info.player_board);
+                transformed.opponent_board = transform(j, board_info.opponent_board);
+                assert_eq!(canonical(&transformed).0, key);
+                let mut remaining = legal_board;
+                while remaining != 0 {
+                    let mask = remaining & remaining.wrapping_neg();
+                    remaining ^= mask;
+                    assert_eq!(canonical_move(&transformed, transform(j, mask)), canonical_move(board_info, mask));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn opening_moves_are_all_the_same_canonical_move() {
+        let board_info = BoardInfo::new();
+        let moves: Vec<u64> = ["F5", "E6", "D3", "C4"].iter().map(|s| canonical_move(&board_info, square(s))).collect();
+        assert!(moves.iter().all(|&m| m == moves[0]));
+    }
+}
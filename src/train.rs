@@ -0,0 +1,184 @@
+// 棋譜の局面と最終石差から評価関数の重みを求める（学習）
+// eval_normalの重みは最小二乗法で，パターンの重みは勾配降下法で求める
+
+use std::fs;
+use std::io::Write;
+
+use crate::board::*;
+use crate::config::Config;
+use crate::eval::*;
+use crate::pattern::*;
+use crate::selfplay::GameRecord;
+
+// 学習で，最終石差1石を何点とするか（eval_normalの重みの単位）
+pub const TRAIN_SCORE_SCALE: f64 = 100.0;
+
+// 学習に用いる1局面
+pub struct Sample {
+    pub board_info: BoardInfo,
+    pub score: i32, // 手番側からみた最終石差
+}
+
+// 学習データ（コーパス）の1行にする，書式は「手番側の盤面 相手側の盤面 手番側からみた最終石差」（盤面は16進数）
+pub fn sample_to_line(sample: &Sample) -> String {
+    return format!("{:016x} {:016x} {}", sample.board_info.player_board, sample.board_info.opponent_board, sample.score);
+}
+
+// コーパスを読み込む，#以降はコメント
+pub fn parse_corpus(text: &str) -> Result<Vec<Sample>, String> {
+    let mut samples = Vec::<Sample>::new();
+    for (i, line) in text.lines().enumerate() {
+        let line = line.split('#').next().unwrap().trim();
+        if line.is_empty() {
+            continue;
+        }
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.len() != 3 {
+            return Err(format!("line {}: expected 3 fields", i + 1));
+        }
+        let player_board = u64::from_str_radix(fields[0], 16).map_err(|_| format!("line {}: invalid board", i + 1))?;
+        let opponent_board = u64::from_str_radix(fields[1], 16).map_err(|_| format!("line {}: invalid board", i + 1))?;
+        let score: i32 = fields[2].parse().map_err(|_| format!("line {}: invalid score", i + 1))?;
+        let board_info = boards_to_board_info(player_board, opponent_board).map_err(|e| format!("line {}: {}", i + 1, e))?;
+        samples.push(Sample {
+            board_info,
+            score,
+        });
+    }
+    return Ok(samples);
+}
+
+// ファイルからコーパスを読み込む
+pub fn load_corpus(path: &str) -> Result<Vec<Sample>, String> {
+    let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+    return parse_corpus(&text).map_err(|e| format!("{}: {}", path, e));
+}
+
+// コーパスをファイルの末尾に書き足す
+pub fn append_corpus(path: &str, samples: &[Sample]) -> Result<(), String> {
+    let mut file = fs::OpenOptions::new().create(true).append(true).open(path).map_err(|e| format!("{}: {}", path, e))?;
+    for sample in samples.iter() {
+        writeln!(file, "{}", sample_to_line(sample)).map_err(|e| format!("{}: {}", path, e))?;
+    }
+    return Ok(());
+}
+
+// startの局面から始まった対局の記録から，終局までの各局面とその最終石差を取り出す
+pub fn samples_from_record(start: &BoardInfo, record: &GameRecord) -> Vec<Sample> {
+    let mut samples = Vec::<Sample>::new();
+    let mut board_info = start.clone();
+    let black_diff = record.black_count as i32 - record.white_count as i32;
+    for mov in record.moves.iter() {
+        samples.push(Sample {
+            board_info: board_info.clone(),
+            score: if board_info.now_turn == BLACK {black_diff} else {-black_diff},
+        });
+        place(mov.place_bit, &mut board_info);
+        swap(&mut board_info);
+    }
+    return samples;
+}
+
+// eval_normalの重みを最小二乗法で求める（中盤の評価関数なので，configで終盤の読み切りに入る前の局面だけを用いる）
+pub fn train_normal(samples: &[Sample], config: &Config) -> Result<[i32; NORMAL_FEATURES], String> {
+    // 正規方程式 (X^T X) w = X^T y を作る
+    let mut xtx = [[0f64; NORMAL_FEATURES]; NORMAL_FEATURES];
+    let mut xty = [0f64; NORMAL_FEATURES];
+    let mut count = 0;
+    for sample in samples.iter() {
+        let empties = BOARDSIZE - (sample.board_info.player_board | sample.board_info.opponent_board).count_ones() as i32;
+        if empties <= config.depth(EVAL_WIN) as i32 || sample.board_info.player_board == 0 || sample.board_info.opponent_board == 0 {
+            continue;
+        }
+        let features = normal_features(&sample.board_info);
+        let y = sample.score as f64 * TRAIN_SCORE_SCALE;
+        for i in 0..NORMAL_FEATURES {
+            for j in 0..NORMAL_FEATURES {
+                xtx[i][j] += (features[i] * features[j]) as f64;
+            }
+            xty[i] += features[i] as f64 * y;
+        }
+        count += 1;
+    }
+    if count < NORMAL_FEATURES {
+        return Err(format!("too few midgame positions: {}", count));
+    }
+
+    // ガウスの消去法（部分ピボット選択）で解く
+    for col in 0..NORMAL_FEATURES {
+        let pivot = (col..NORMAL_FEATURES).max_by(|&a, &b| xtx[a][col].abs().partial_cmp(&xtx[b][col].abs()).unwrap()).unwrap();
+        if xtx[pivot][col].abs() < 1e-9 {
+            return Err(format!("{} never varies in the corpus", NORMAL_WEIGHT_NAMES[col]));
+        }
+        xtx.swap(col, pivot);
+        xty.swap(col, pivot);
+        for row in 0..NORMAL_FEATURES {
+            if row == col {
+                continue;
+            }
+            let pivot_row = xtx[col];
+            let factor = xtx[row][col] / pivot_row[col];
+            for (k, value) in xtx[row].iter_mut().enumerate().skip(col) {
+                *value -= factor * pivot_row[k];
+            }
+            xty[row] -= factor * xty[col];
+        }
+    }
+
+    let mut weights = [0i32; NORMAL_FEATURES];
+    for i in 0..NORMAL_FEATURES {
+        weights[i] = (xty[i] / xtx[i][i]).round() as i32;
+    }
+    return Ok(weights);
+}
+
+// パターンの重みを勾配降下法で求める
+// 各重みは，それが現れる局面の誤差の平均にrateをかけた分だけ動かす．epochs回繰り返す
+pub fn train_pattern(samples: &[Sample], epochs: usize, rate: f32) -> PatternWeights {
+    let size = phase_size();
+    let mut weights = vec![0f32; PATTERN_PHASES * size];
+
+    // 各局面の特徴を先に求めておく（重みの中での位置にする）
+    let mut sample_features = Vec::<Vec<usize>>::with_capacity(samples.len());
+    let mut features = Vec::<usize>::new();
+    for sample in samples.iter() {
+        pattern_features(&sample.board_info, &mut features);
+        let base = phase_of(&sample.board_info) * size;
+        sample_features.push(features.iter().map(|&f| base + f).collect());
+    }
+
+    let mut gradient = vec![0f32; weights.len()];
+    let mut count = vec![0u32; weights.len()];
+    for epoch in 0..epochs {
+        for g in gradient.iter_mut() {
+            *g = 0.0;
+        }
+        for c in count.iter_mut() {
+            *c = 0;
+        }
+        let mut squared_error: f64 = 0.0;
+        for (sample, features) in samples.iter().zip(sample_features.iter()) {
+            let prediction: f32 = features.iter().map(|&f| weights[f]).sum();
+            let error = sample.score as f32 - prediction;
+            squared_error += (error * error) as f64;
+            for &f in features.iter() {
+                gradient[f] += error;
+                count[f] += 1;
+            }
+        }
+        for i in 0..weights.len() {
+            if count[i] > 0 {
+                weights[i] += rate * gradient[i] / count[i] as f32;
+            }
+        }
+        println!("epoch {}: mean squared error {:.3}", epoch + 1, squared_error / samples.len() as f64);
+    }
+
+    let mut pattern_weights = PatternWeights::new();
+    for phase in 0..PATTERN_PHASES {
+        for feature in 0..size {
+            pattern_weights.set(phase, feature, weights[phase * size + feature]);
+        }
+    }
+    return pattern_weights;
+}
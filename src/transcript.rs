@@ -0,0 +1,188 @@
+// 棋譜（transcript）の書き出しと読み込み
+// 初期局面からの手を"f5d6c3d3c4"のように区切らずに並べた形式で，パスは書かない（読み込むときに自動で補う）
+// 1行に1局を書いたファイルとしてまとめて保存できる（#以降はコメント）
+
+use std::fs;
+use std::io::Write;
+
+use crate::board::*;
+
+// 1局分の棋譜
+#[derive(Clone)]
+pub struct Transcript {
+    pub moves: Vec<u64>,           // 打った手（パスは0）
+    pub positions: Vec<BoardInfo>, // positions[i]はmoves[i]を打つ前の局面，最後の要素は最後の手を打ったあとの局面
+}
+
+impl Transcript {
+    // 初期局面から手順movesを打って棋譜にする
+    // 打てない手があればエラー，パスするしかない局面では（movesにパスが書かれていなければ）パスを補う
+    pub fn from_moves(moves: &[u64]) -> Result<Transcript, String> {
+        let mut board_info = BoardInfo::new();
+        let mut transcript = Transcript {
+            moves: Vec::new(),
+            positions: vec![board_info.clone()],
+        };
+        for (i, &place_bit) in moves.iter().enumerate() {
+            if is_game_over(&board_info) {
+                return Err(format!("move {}: the game is already over: {}", i + 1, move_to_string(place_bit)));
+            }
+            if place_bit != 0 && make_legal_board(&board_info) == 0 { // パスを補う
+                place(0, &mut board_info);
+                swap(&mut board_info);
+                transcript.moves.push(0);
+                transcript.positions.push(board_info.clone());
+            }
+            if place(place_bit, &mut board_info) == PLACE_ERR {
+                return Err(format!("move {}: illegal move: {}", i + 1, move_to_string(place_bit)));
+            }
+            swap(&mut board_info);
+            transcript.moves.push(place_bit);
+            transcript.positions.push(board_info.clone());
+        }
+        // 最後の手のあとにパスするしかなければ，それも補う
+        if make_legal_board(&board_info) == 0 && !is_game_over(&board_info) {
+            place(0, &mut board_info);
+            swap(&mut board_info);
+            transcript.moves.push(0);
+            transcript.positions.push(board_info);
+        }
+        return Ok(transcript);
+    }
+
+    // "f5d6c3"のような文字列から棋譜を読み込む（大文字も可，空白は無視，パスを"PASS"と書いてもよい）
+    pub fn parse(text: &str) -> Result<Transcript, String> {
+        let chars: Vec<char> = text.chars().filter(|c| !c.is_whitespace()).collect();
+        let mut moves = Vec::<u64>::new();
+        let mut i = 0;
+        while i < chars.len() {
+            let rest: String = chars[i..].iter().take(4).collect();
+            if rest.eq_ignore_ascii_case("PASS") {
+                moves.push(0);
+                i += 4;
+                continue;
+            }
+            let s: String = chars[i..].iter().take(2).collect();
+            moves.push(string_to_move(&s)?);
+            i += 2;
+        }
+        return Transcript::from_moves(&moves);
+    }
+
+    // parse()で読み込める形式（小文字で区切らずに並べ，パスは書かない）の文字列にする
+    pub fn to_text(&self) -> String {
+        return self.moves.iter().filter(|&&place_bit| place_bit != 0)
+            .map(|&place_bit| move_to_string(place_bit).to_lowercase()).collect();
+    }
+
+    // 最後の手を打ったあとの局面
+    pub fn last_position(&self) -> &BoardInfo {
+        return self.positions.last().unwrap();
+    }
+
+    // 終局まで打たれているか
+    pub fn is_finished(&self) -> bool {
+        return is_game_over(self.last_position());
+    }
+}
+
+// 1行に1局の棋譜を書いたテキストを読み込む，#以降はコメント
+pub fn parse_transcripts(text: &str) -> Result<Vec<Transcript>, String> {
+    let mut transcripts = Vec::<Transcript>::new();
+    for (i, line) in text.lines().enumerate() {
+        let line = line.split('#').next().unwrap().trim();
+        if line.is_empty() {
+            continue;
+        }
+        transcripts.push(Transcript::parse(line).map_err(|e| format!("line {}: {}", i + 1, e))?);
+    }
+    return Ok(transcripts);
+}
+
+// 棋譜のファイルを読み込む
+pub fn load_transcripts(path: &str) -> Result<Vec<Transcript>, String> {
+    let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+    return parse_transcripts(&text).map_err(|e| format!("{}: {}", path, e));
+}
+
+// 棋譜をファイルに1行書き足す（終局していれば，黒と白の石数をコメントとして添える）
+pub fn append_transcript(path: &str, transcript: &Transcript) -> Result<(), String> {
+    let mut line = transcript.to_text();
+    if transcript.is_finished() {
+        let (black_count, white_count, _) = get_result(transcript.last_position());
+        line += &format!(" # {}-{}", black_count, white_count);
+    }
+    let mut file = fs::OpenOptions::new().create(true).append(true).open(path).map_err(|e| format!("{}: {}", path, e))?;
+    return writeln!(file, "{}", line).map_err(|e| format!("{}: {}", path, e));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::random::{random_game, Rng};
+
+    fn same_position(a: &BoardInfo, b: &BoardInfo) -> bool {
+        return a.now_turn == b.now_turn && a.player_board == b.player_board && a.opponent_board == b.opponent_board;
+    }
+
+    #[test]
+    fn text_round_trip_restores_moves_and_passes() {
+        let mut rng = Rng::new(21);
+        let mut passes = 0;
+        for _ in 0..200 {
+            let moves = random_game(&mut rng).1;
+            passes += moves.iter().filter(|&&place_bit| place_bit == 0).count();
+            let transcript = Transcript::from_moves(&moves).unwrap();
+            assert_eq!(transcript.moves, moves);
+            assert_eq!(transcript.positions.len(), moves.len() + 1);
+            assert!(transcript.is_finished());
+
+            let text = transcript.to_text();
+            assert!(!text.contains("pass"));
+            let parsed = Transcript::parse(&text).unwrap();
+            assert_eq!(parsed.moves, moves, "{}", text); // 書かれていないパスも補われる
+            assert!(transcript.positions.iter().zip(parsed.positions.iter()).all(|(a, b)| same_position(a, b)));
+            assert_eq!(parsed.to_text(), text);
+        }
+        assert!(passes > 0, "no game with a pass was generated");
+    }
+
+    #[test]
+    fn parse_accepts_upper_case_spaces_and_pass() {
+        let transcript = Transcript::parse("F5 d6 C3").unwrap();
+        assert_eq!(transcript.to_text(), "f5d6c3");
+        assert_eq!(transcript.last_position().now_turn, WHITE);
+        assert!(!transcript.is_finished());
+        assert!(Transcript::parse("f5PASS").is_err()); // 打てる手があるのにパスはできない
+    }
+
+    #[test]
+    fn parse_rejects_illegal_moves() {
+        assert!(Transcript::parse("a1").is_err());
+        assert!(Transcript::parse("f5f5").is_err());
+        assert!(Transcript::parse("f5d").is_err());
+        assert!(Transcript::parse("z9").is_err());
+    }
+
+    #[test]
+    fn transcripts_file_round_trip() {
+        let mut rng = Rng::new(2121);
+        let path = std::env::temp_dir().join(format!("reversi-transcript-test-{}.txt", std::process::id()));
+        let path = path.to_str().unwrap();
+        let _ = fs::remove_file(path);
+        let transcripts: Vec<Transcript> = (0..3).map(|_| Transcript::from_moves(&random_game(&mut rng).1).unwrap()).collect();
+        for transcript in transcripts.iter() {
+            append_transcript(path, transcript).unwrap();
+        }
+        append_transcript(path, &Transcript::parse("f5d6").unwrap()).unwrap(); // 終局していない棋譜
+        let loaded = load_transcripts(path).unwrap();
+        fs::remove_file(path).unwrap();
+
+        assert_eq!(loaded.len(), 4);
+        for (a, b) in transcripts.iter().zip(loaded.iter()) {
+            assert_eq!(a.moves, b.moves);
+        }
+        assert_eq!(loaded[3].to_text(), "f5d6");
+        assert_eq!(parse_transcripts("# comment only\n\nf5 # opening\n").unwrap().len(), 1);
+    }
+}
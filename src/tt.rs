@@ -0,0 +1,200 @@
+// 置換表（Zobrist hashによる）
+// 複数のスレッドから同時に読み書きできるように，ロックを使わずAtomicU64で実装する
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::board::*;
+
+// 置換表に保存する評価値の種類
+pub const BOUND_NONE: u8 = 0;
+pub const BOUND_EXACT: u8 = 1; // 正確な値
+pub const BOUND_LOWER: u8 = 2; // 下限（βカットされた）
+pub const BOUND_UPPER: u8 = 3; // 上限（αを超えなかった）
+
+// 置換表のエントリ数（2のべき乗）のデフォルト
+pub const TT_SIZE_BITS: u32 = 20;
+
+// 置換表を使う残り深さの下限
+pub const TT_MIN_DEPTH: i8 = 2;
+
+// 最善手がないことを表すインデックス
+const NO_MOVE: u64 = 64;
+
+// Zobrist hash用の乱数を生成する（splitmix64）
+const fn splitmix64(seed: u64) -> u64 {
+    let mut z = seed.wrapping_add(0x9e3779b97f4a7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    return z ^ (z >> 31);
+}
+
+// 各マスの乱数を8マス（1バイト）ごとにまとめて，256通りの並びに対するXORを前計算しておく
+const fn make_zobrist_table(offset: u64) -> [[u64; 256]; 8] {
+    let mut table = [[0u64; 256]; 8];
+    let mut byte = 0;
+    while byte < 8 {
+        let mut pattern = 0;
+        while pattern < 256 {
+            let mut hash = 0;
+            let mut bit = 0;
+            while bit < 8 {
+                if pattern & (1 << bit) != 0 {
+                    hash ^= splitmix64(offset + (byte * 8 + bit) as u64);
+                }
+                bit += 1;
+            }
+            table[byte][pattern] = hash;
+            pattern += 1;
+        }
+        byte += 1;
+    }
+    return table;
+}
+
+const ZOBRIST_PLAYER: [[u64; 256]; 8] = make_zobrist_table(0);
+const ZOBRIST_OPPONENT: [[u64; 256]; 8] = make_zobrist_table(64);
+const ZOBRIST_SIDE: u64 = splitmix64(128); // 白番のときにXORする
+
+// 盤面のZobrist hashを計算する
+pub fn zobrist_hash(board_info: &BoardInfo) -> u64 {
+    let mut hash: u64 = 0;
+    for i in 0..8 {
+        hash ^= ZOBRIST_PLAYER[i][((board_info.player_board >> (i * 8)) & 0xff) as usize];
+        hash ^= ZOBRIST_OPPONENT[i][((board_info.opponent_board >> (i * 8)) & 0xff) as usize];
+    }
+    if board_info.now_turn == WHITE {
+        hash ^= ZOBRIST_SIDE;
+    }
+    return hash;
+}
+
+// 置換表から取り出した情報
+pub struct TTEntry {
+    pub score: i32,
+    pub depth: i8,
+    pub bound: u8,
+    pub best_move: u64, // 最善手のビット（なければ0）
+}
+
+// 置換表の1エントリ，keyにはhashとdataのXORを保存して，書き込みが混ざったエントリを検出する
+struct Slot {
+    key: AtomicU64,
+    data: AtomicU64,
+}
+
+pub struct TranspositionTable {
+    slots: Vec<Slot>,
+    mask: u64,
+}
+
+// dataのビット配置: score(32bit) | depth(8bit) | bound(8bit) | best_move(8bit)
+fn pack(score: i32, depth: i8, bound: u8, best_move: u64) -> u64 {
+    let index = if best_move == 0 {NO_MOVE} else {best_move.trailing_zeros() as u64};
+    return (score as u32 as u64) << 32 | (depth as u8 as u64) << 24 | (bound as u64) << 16 | index << 8;
+}
+
+fn unpack(data: u64) -> TTEntry {
+    let index = (data >> 8) & 0xff;
+    return TTEntry {
+        score: (data >> 32) as u32 as i32,
+        depth: ((data >> 24) & 0xff) as u8 as i8,
+        bound: ((data >> 16) & 0xff) as u8,
+        best_move: if index == NO_MOVE {0} else {1 << index},
+    };
+}
+
+impl TranspositionTable {
+    // 2^bits個のエントリをもつ置換表を作る
+    pub fn new(bits: u32) -> TranspositionTable {
+        let size = 1usize << bits;
+        let mut slots = Vec::with_capacity(size);
+        for _ in 0..size {
+            slots.push(Slot {
+                key: AtomicU64::new(0),
+                data: AtomicU64::new(0),
+            });
+        }
+        return TranspositionTable {
+            slots,
+            mask: (size - 1) as u64,
+        };
+    }
+
+    // hashに対応するエントリを探す
+    pub fn probe(&self, hash: u64) -> Option<TTEntry> {
+        let slot = &self.slots[(hash & self.mask) as usize];
+        let data = slot.data.load(Ordering::Relaxed);
+        let key = slot.key.load(Ordering::Relaxed);
+        if key ^ data != hash {
+            return None;
+        }
+        let entry = unpack(data);
+        if entry.bound == BOUND_NONE {
+            return None;
+        }
+        return Some(entry);
+    }
+
+    // エントリを保存する，深く探索したエントリはなるべく残す
+    pub fn store(&self, hash: u64, score: i32, depth: i8, bound: u8, best_move: u64) {
+        let slot = &self.slots[(hash & self.mask) as usize];
+        let old_data = slot.data.load(Ordering::Relaxed);
+        let old_key = slot.key.load(Ordering::Relaxed);
+        if old_key ^ old_data == hash && unpack(old_data).depth > depth && bound != BOUND_EXACT {
+            return;
+        }
+        let data = pack(score, depth, bound, best_move);
+        slot.data.store(data, Ordering::Relaxed);
+        slot.key.store(hash ^ data, Ordering::Relaxed);
+    }
+
+    // 全エントリを消去する
+    pub fn clear(&self) {
+        for slot in self.slots.iter() {
+            slot.key.store(0, Ordering::Relaxed);
+            slot.data.store(0, Ordering::Relaxed);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn store_and_probe_round_trip() {
+        let tt = TranspositionTable::new(8);
+        let hash = zobrist_hash(&BoardInfo::new());
+        assert!(tt.probe(hash).is_none());
+        for &(score, depth, bound, best_move) in [(-64, 1, BOUND_EXACT, 0), (12345, 7, BOUND_UPPER, 1), (std::i32::MIN+1, 60, BOUND_LOWER, 1 << 63)].iter() {
+            tt.store(hash, score, depth, bound, best_move);
+            let entry = tt.probe(hash).unwrap();
+            assert_eq!((entry.score, entry.depth, entry.bound, entry.best_move), (score, depth, bound, best_move));
+        }
+        assert!(tt.probe(hash ^ 1 << 40).is_none()); // 同じスロットの別の局面
+        tt.clear();
+        assert!(tt.probe(hash).is_none());
+    }
+
+    #[test]
+    fn store_keeps_deeper_bounds() {
+        let tt = TranspositionTable::new(8);
+        let hash = 0x0123456789abcdef;
+        tt.store(hash, 10, 8, BOUND_LOWER, 0);
+        tt.store(hash, 20, 3, BOUND_UPPER, 0); // 浅い上限・下限では上書きしない
+        assert_eq!(tt.probe(hash).unwrap().depth, 8);
+        tt.store(hash, 30, 3, BOUND_EXACT, 0); // 正確な値は上書きする
+        assert_eq!((tt.probe(hash).unwrap().score, tt.probe(hash).unwrap().depth), (30, 3));
+    }
+
+    #[test]
+    fn hash_depends_on_the_side_to_move() {
+        let board_info = BoardInfo::new();
+        let mut swapped = board_info.clone();
+        swap(&mut swapped);
+        let mut white = board_info.clone();
+        white.now_turn = WHITE;
+        assert_ne!(zobrist_hash(&board_info), zobrist_hash(&swapped));
+        assert_ne!(zobrist_hash(&board_info), zobrist_hash(&white));
+    }
+}
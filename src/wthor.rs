@@ -0,0 +1,197 @@
+// WTHORの棋譜データベース（.wtb）の読み込み
+// 16バイトのヘッダのあとに1局68バイトの記録が並ぶ（数値はリトルエンディアン）
+// 1局の記録は大会・黒・白の番号（各2バイト），黒の石数，理論上の黒の石数，60手分の手（10*行+列，1始まり，0なら終わり，パスは書かれない）
+
+use std::fs;
+
+use crate::board::*;
+use crate::train::Sample;
+use crate::transcript::Transcript;
+
+const WTHOR_HEADER_SIZE: usize = 16;
+const WTHOR_GAME_SIZE: usize = 68;
+
+// .wtbの1局
+#[derive(Clone)]
+pub struct WthorGame {
+    pub tournament: u16,        // 大会の番号（WTHOR.TRNの何番目か）
+    pub black_player: u16,      // 黒の対局者の番号（WTHOR.JOUの何番目か）
+    pub white_player: u16,      // 白の対局者の番号
+    pub black_score: u8,        // 終局時の黒の石数（空きマスは勝った側に数える）
+    pub theoretical_score: u8,  // 最善を尽くしたときの黒の石数
+    pub moves: Vec<u64>,        // 打った手（パスは含まない）
+}
+
+// .wtbのファイル1つ
+pub struct WthorDatabase {
+    pub year: u16,              // 対局の年
+    pub games: Vec<WthorGame>,
+}
+
+fn read_u16(data: &[u8], offset: usize) -> u16 {
+    return u16::from_le_bytes([data[offset], data[offset+1]]);
+}
+
+fn read_u32(data: &[u8], offset: usize) -> u32 {
+    return u32::from_le_bytes([data[offset], data[offset+1], data[offset+2], data[offset+3]]);
+}
+
+// 1局68バイトの記録を読む
+fn parse_game(record: &[u8]) -> Result<WthorGame, String> {
+    let mut moves = Vec::<u64>::new();
+    for &square in record[8..WTHOR_GAME_SIZE].iter() {
+        if square == 0 {
+            break;
+        }
+        let (row, column) = (square / 10, square % 10);
+        if !(1..=8).contains(&row) || !(1..=8).contains(&column) {
+            return Err(format!("invalid move: {}", square));
+        }
+        moves.push(1u64 << (63 - ((row as i32 - 1) * LINESIZE + column as i32 - 1)));
+    }
+    return Ok(WthorGame {
+        tournament: read_u16(record, 0),
+        black_player: read_u16(record, 2),
+        white_player: read_u16(record, 4),
+        black_score: record[6],
+        theoretical_score: record[7],
+        moves,
+    });
+}
+
+impl WthorGame {
+    // place()で打ち直して棋譜にする（パスは補われる）
+    pub fn replay(&self) -> Result<Transcript, String> {
+        return Transcript::from_moves(&self.moves);
+    }
+
+    // 黒からみた最終石差
+    pub fn black_diff(&self) -> i32 {
+        return 2 * self.black_score as i32 - BOARDSIZE;
+    }
+
+    // 終局までの各局面（最後の手を打ったあとの局面は除く）と，その手番側からみた最終石差
+    pub fn samples(&self) -> Result<Vec<Sample>, String> {
+        let transcript = self.replay()?;
+        let black_diff = self.black_diff();
+        return Ok(transcript.positions[..transcript.moves.len()].iter().map(|board_info| Sample {
+            board_info: board_info.clone(),
+            score: if board_info.now_turn == BLACK {black_diff} else {-black_diff},
+        }).collect());
+    }
+}
+
+// .wtbの中身を読み込む
+pub fn parse_wthor(data: &[u8]) -> Result<WthorDatabase, String> {
+    if data.len() < WTHOR_HEADER_SIZE {
+        return Err("too short for a WTHOR header".to_string());
+    }
+    let count = read_u32(data, 4) as usize;
+    let board_size = data[12];
+    if board_size != 0 && board_size != 8 {
+        return Err(format!("unsupported board size: {}", board_size));
+    }
+    if data.len() < WTHOR_HEADER_SIZE + count * WTHOR_GAME_SIZE {
+        return Err(format!("expected {} games but the file is too short", count));
+    }
+    let mut games = Vec::<WthorGame>::new();
+    for i in 0..count {
+        let offset = WTHOR_HEADER_SIZE + i * WTHOR_GAME_SIZE;
+        games.push(parse_game(&data[offset..offset+WTHOR_GAME_SIZE]).map_err(|e| format!("game {}: {}", i + 1, e))?);
+    }
+    return Ok(WthorDatabase {
+        year: read_u16(data, 10),
+        games,
+    });
+}
+
+// .wtbのファイルを読み込む
+pub fn load_wthor(path: &str) -> Result<WthorDatabase, String> {
+    let data = fs::read(path).map_err(|e| format!("{}: {}", path, e))?;
+    return parse_wthor(&data).map_err(|e| format!("{}: {}", path, e));
+}
+
+// 各局の局面と最終石差をまとめて取り出す，返り値は(学習データ，打ち直せなかった局の数)
+pub fn wthor_samples(database: &WthorDatabase) -> (Vec<Sample>, usize) {
+    let mut samples = Vec::<Sample>::new();
+    let mut invalid = 0;
+    for game in database.games.iter() {
+        match game.samples() {
+            Ok(game_samples) => samples.extend(game_samples),
+            Err(_) => invalid += 1,
+        };
+    }
+    return (samples, invalid);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // count局分のヘッダ（年は2003，盤の大きさは8）
+    fn header(count: u32) -> Vec<u8> {
+        let mut data = vec![0u8; WTHOR_HEADER_SIZE];
+        data[4..8].copy_from_slice(&count.to_le_bytes());
+        data[10..12].copy_from_slice(&2003u16.to_le_bytes());
+        data[12] = 8;
+        return data;
+    }
+
+    // 1局分の記録
+    fn record(tournament: u16, black_player: u16, white_player: u16, black_score: u8, moves: &[u8]) -> Vec<u8> {
+        let mut data = vec![0u8; WTHOR_GAME_SIZE];
+        data[0..2].copy_from_slice(&tournament.to_le_bytes());
+        data[2..4].copy_from_slice(&black_player.to_le_bytes());
+        data[4..6].copy_from_slice(&white_player.to_le_bytes());
+        data[6] = black_score;
+        data[7] = black_score - 2;
+        data[8..8+moves.len()].copy_from_slice(moves);
+        return data;
+    }
+
+    #[test]
+    fn parse_wthor_reads_a_hand_built_record() {
+        let mut data = header(2);
+        data.extend(record(3, 5, 7, 40, &[56, 64, 33])); // f5 d6 c3
+        data.extend(record(1, 2, 4, 20, &[11])); // a1には打てない
+        let database = parse_wthor(&data).unwrap();
+        assert_eq!(database.year, 2003);
+        assert_eq!(database.games.len(), 2);
+
+        let game = &database.games[0];
+        assert_eq!((game.tournament, game.black_player, game.white_player), (3, 5, 7));
+        assert_eq!((game.black_score, game.theoretical_score), (40, 38));
+        assert_eq!(line_to_string(&game.moves), "F5 D6 C3");
+        assert_eq!(game.replay().unwrap().to_text(), "f5d6c3");
+        assert_eq!(game.black_diff(), 16);
+        let samples = game.samples().unwrap();
+        assert_eq!(samples.iter().map(|sample| sample.score).collect::<Vec<i32>>(), vec![16, -16, 16]);
+
+        let (samples, invalid) = wthor_samples(&database);
+        assert_eq!((samples.len(), invalid), (3, 1));
+    }
+
+    #[test]
+    fn parse_wthor_rejects_a_bad_square() {
+        for &square in [9u8, 19, 90, 255].iter() {
+            let mut data = header(1);
+            data.extend(record(0, 0, 0, 32, &[56, square]));
+            let e = parse_wthor(&data).err().unwrap();
+            assert!(e.contains("game 1") && e.contains(&format!("invalid move: {}", square)), "{}", e);
+        }
+    }
+
+    #[test]
+    fn parse_wthor_rejects_a_truncated_file() {
+        assert!(parse_wthor(&header(0)[..10]).is_err());
+        let mut data = header(2);
+        data.extend(record(0, 0, 0, 32, &[56]));
+        assert!(parse_wthor(&data).err().unwrap().contains("too short"));
+        data.extend(record(0, 0, 0, 32, &[56]));
+        assert_eq!(parse_wthor(&data).unwrap().games.len(), 2);
+
+        let mut data = header(0);
+        data[12] = 10; // 10x10の盤
+        assert!(parse_wthor(&data).is_err());
+    }
+}